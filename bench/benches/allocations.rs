@@ -0,0 +1,62 @@
+//! Allocations per sentence, a separate target, so the counting allocator
+//! doesn't slow down other benches
+
+mod common;
+
+use ::std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use common::SENTENCES;
+use langram::{DetectorBuilder, DetectorScratch, ModelsStorage};
+
+/// Counts allocations, to compare allocations per call
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations_per_sentence(mut f: impl FnMut(&str)) -> f64 {
+    let start = ALLOCATIONS.load(Ordering::Relaxed);
+    SENTENCES.iter().for_each(|sentence| f(sentence));
+    (ALLOCATIONS.load(Ordering::Relaxed) - start) as f64 / SENTENCES.len() as f64
+}
+
+fn main() {
+    let models_storage = ModelsStorage::new().unwrap();
+    let detector = DetectorBuilder::new(&models_storage).build();
+    let mut scratch = DetectorScratch::new();
+    // warm up the scratch
+    SENTENCES.iter().for_each(|sentence| {
+        black_box(detector.probabilities_with_scratch(sentence, &mut scratch));
+    });
+
+    println!(
+        "Allocations per sentence: probabilities {:.1}, probabilities_with_scratch {:.1}",
+        allocations_per_sentence(|sentence| {
+            black_box(detector.probabilities(sentence));
+        }),
+        allocations_per_sentence(|sentence| {
+            black_box(detector.probabilities_with_scratch(sentence, &mut scratch));
+        }),
+    );
+}
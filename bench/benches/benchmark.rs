@@ -1,6 +1,9 @@
+mod common;
+
 use ::std::{collections::HashSet, hint::black_box};
+use common::SENTENCES;
 use criterion::{criterion_group, criterion_main, Criterion};
-use langram::{DetectorBuilder, ModelsStorage, ScriptLanguage};
+use langram::{DetectorBuilder, DetectorScratch, ModelsStorage, ScriptLanguage};
 
 // This is the common subset of languages that is supported by all
 // language detection libraries in this benchmark.
@@ -23,23 +26,15 @@ const COMMON_LANGUAGES: &[ScriptLanguage] = &[
     // ScriptLanguage::Vietnamese,
 ];
 
-const SENTENCES: &[&str] = &[
-    "و في نفس الوقت أقول بأن الشيخ صالح لم يشر إلى مسؤولية الدولة التي تسمح لمواطنيها بملكية قنوات تبث ما تبث بل إنه حصر المسؤولية على ملاك هذه القنوات.",
-    // "102年度彰化县劳工运动会暨园游会于12月1日(星期日)上午在县立体育场盛大登场，来自全县共61个事业单位及职业工会超过3,000多位选手参加，运动会场将展开一系列的竞技对战。",
-    "Aan de fysieke gesteldheid van de aspirant-beoefenaar worden geen bijzondere eisen gesteld anders dan een goede gezondheid.",
-    "Here, in a region abundant with natural beauty, golfers will surely be rewarded with an exceptional golf experience.",
-    "Les affranchissements étaient très rares et s'ils accordaient la liberté à l'ancien esclave, ils ne lui conféraient pas le titre de citoyen.",
-    "Natürlich war sie kein Pferd, dachte sie, aber warum wurde sie dann geritten, hatte einen Reiter zu tragen, war gesattelt, bekam Sporen und Lederpeitsche?",
-    "अब इन्हें एक अलग प्लेट में निकाल कर गरमा-गरम आलू की सब्जी, हरे धनिये की चटनी या मीठी चटनी के साथ परोस कर खाइये और सबको खिलाइये।",
-    "Alla fine del secolo cambiarono nome, divenendo uno Capitano e l’altro Difensore, ma mantenendo le stesse caratteristiche degli anni precedenti.",
-    // "・京都大学施設に電離圏における電子数などの状況を取得可能なイオノゾンデ受信機（斜入射観測装置）を設置することで、新たな観測手法が地震先行現象検出に資するかを検証する。",
-    // "아울러 가장 많은 수가 일하고 있는 직업은 곡식작물 재배자(109만6천명)로 조사됐고, 상점판매 및 관리인(97만8천명), 상점판매원(87만3천명), 일반 영업원(59만명) 등이 뒤를 이었다.",
-    "Dizer que não estou, significaria explicar porquê e não me apetece nada desfiar o rosário das minhas lamentações.",
-    "То есть присяжные не сочли возможным осудить за соучастие в убийстве и убийство людей, доказательства вины которых не были предъявлены.",
-    "Con frecuencia creo que Francia es malinterpretada, seala, aludiendo a la imagen que tiene el pas internacionalmente en materia de tica de trabajo.",
-    "Med dagens stadshusmajoritet är det övervikt för ett enplanstorg med bostäder, alltså för en ombyggnad i linje med alternativ maxi.",
-    "Mezuniyet hediyesi olarak yerleşkenin kuzey batı bölümüne dikilmiş vişnelerin meyvesini, tohumunu almışlardır.",
-    // "Cuốn sách là cẩm nang hữu ích để tham khảo và học hỏi, giúp các bà mẹ Việt tự tin hơn trong cách dạy con.",
+/// Long texts of distinct sentences, because repeated ngrams are deduplicated
+/// (texts of a repeated sentence are as fast as the sentence).
+const LONG_TEXTS: &[&str] = &[
+    "It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife. However little known the feelings or views of such a man may be on his first entering a neighbourhood, this truth is so well fixed in the minds of the surrounding families, that he is considered the rightful property of some one or other of their daughters. My dear Mr. Bennet, said his lady to him one day, have you heard that Netherfield Park is let at last? Mr. Bennet replied that he had not. But it is, returned she; for Mrs. Long has just been here, and she told me all about it. Mr. Bennet made no answer. Do you not want to know who has taken it? cried his wife impatiently. You want to tell me, and I have no objection to hearing it. This was invitation enough. Why, my dear, you must know, Mrs. Long says that Netherfield is taken by a young man of large fortune from the north of England; that he came down on Monday in a chaise and four to see the place, and was so much delighted with it, that he agreed with Mr. Morris immediately; that he is to take possession before Michaelmas, and some of his servants are to be in the house by the end of next week.",
+    "Als Gregor Samsa eines Morgens aus unruhigen Träumen erwachte, fand er sich in seinem Bett zu einem ungeheueren Ungeziefer verwandelt. Er lag auf seinem panzerartig harten Rücken und sah, wenn er den Kopf ein wenig hob, seinen gewölbten, braunen, von bogenförmigen Versteifungen geteilten Bauch, auf dessen Höhe sich die Bettdecke, zum gänzlichen Niedergleiten bereit, kaum noch erhalten konnte. Seine vielen, im Vergleich zu seinem sonstigen Umfang kläglich dünnen Beine flimmerten ihm hilflos vor den Augen. Was ist mit mir geschehen? dachte er. Es war kein Traum. Sein Zimmer, ein richtiges, nur etwas zu kleines Menschenzimmer, lag ruhig zwischen den vier wohlbekannten Wänden. Über dem Tisch, auf dem eine auseinandergepackte Musterkollektion von Tuchwaren ausgebreitet war, hing das Bild, das er vor kurzem aus einer illustrierten Zeitschrift ausgeschnitten und in einem hübschen, vergoldeten Rahmen untergebracht hatte. Gregors Blick richtete sich dann zum Fenster, und das trübe Wetter, man hörte Regentropfen auf das Fensterblech aufschlagen, machte ihn ganz melancholisch.",
+    "En 1815, M. Charles-François-Bienvenu Myriel était évêque de Digne. C'était un vieillard d'environ soixante-quinze ans; il occupait le siège de Digne depuis 1806. Quoique ce détail ne touche en aucune manière au fond même de ce que nous avons à raconter, il n'est peut-être pas inutile, ne fût-ce que pour être exact en tout, d'indiquer ici les bruits et les propos qui avaient couru sur son compte au moment où il était arrivé dans le diocèse. Vrai ou faux, ce qu'on dit des hommes tient souvent autant de place dans leur vie et surtout dans leur destinée que ce qu'ils font. M. Myriel était fils d'un conseiller au parlement d'Aix; noblesse de robe. On contait de lui que son père, le réservant pour hériter de sa charge, l'avait marié de fort bonne heure, à dix-huit ou vingt ans, suivant un usage assez répandu dans les familles parlementaires. Charles Myriel, nonobstant ce mariage, avait, disait-on, beaucoup fait parler de lui.",
+    "En un lugar de la Mancha, de cuyo nombre no quiero acordarme, no ha mucho tiempo que vivía un hidalgo de los de lanza en astillero, adarga antigua, rocín flaco y galgo corredor. Una olla de algo más vaca que carnero, salpicón las más noches, duelos y quebrantos los sábados, lentejas los viernes, algún palomino de añadidura los domingos, consumían las tres partes de su hacienda. El resto della concluían sayo de velarte, calzas de velludo para las fiestas con sus pantuflos de lo mismo, y los días de entre semana se honraba con su vellori de lo más fino. Tenía en su casa una ama que pasaba de los cuarenta, y una sobrina que no llegaba a los veinte, y un mozo de campo y plaza, que así ensillaba el rocín como tomaba la podadera. Frisaba la edad de nuestro hidalgo con los cincuenta años; era de complexión recia, seco de carnes, enjuto de rostro, gran madrugador y amigo de la caza.",
+    "Все счастливые семьи похожи друг на друга, каждая несчастливая семья несчастлива по-своему. Все смешалось в доме Облонских. Жена узнала, что муж был в связи с бывшею в их доме француженкою-гувернанткой, и объявила мужу, что не может жить с ним в одном доме. Положение это продолжалось уже третий день и мучительно чувствовалось и самими супругами, и всеми членами семьи, и домочадцами. Все члены семьи и домочадцы чувствовали, что нет смысла в их сожительстве и что на каждом постоялом дворе случайно сошедшиеся люди более связаны между собой, чем они, члены семьи и домочадцы Облонских. Жена не выходила из своих комнат, мужа третий день не было дома. Дети бегали по всему дому, как потерянные; англичанка поссорилась с экономкой и написала записку приятельнице, прося приискать ей новое место; повар ушел еще вчера со двора, во время обеда.",
+    "C'era una volta un pezzo di legno. Non era un legno di lusso, ma un semplice pezzo da catasta, di quelli che d'inverno si mettono nelle stufe e nei caminetti per accendere il fuoco e per riscaldare le stanze. Non so come andasse, ma il fatto gli è che un bel giorno questo pezzo di legno capitò nella bottega di un vecchio falegname, il quale aveva nome mastr'Antonio, se non che tutti lo chiamavano maestro Ciliegia, per via della punta del suo naso, che era sempre lustra e paonazza, come una ciliegia matura. Appena maestro Ciliegia ebbe visto quel pezzo di legno, si rallegrò tutto; e dandosi una fregatina di mani per la contentezza, borbottò a mezza voce: questo legno è capitato a tempo; voglio servirmene per fare una gamba di tavolino. Detto fatto, prese subito l'ascia arrotata per cominciare a levargli la scorza e a digrossarlo.",
 ];
 
 fn benchmark_detector(c: &mut Criterion) {
@@ -67,22 +62,18 @@ fn benchmark_detector(c: &mut Criterion) {
     });
     group_all.finish();
 
-    /* let mut group2 = c.benchmark_group("Detector all languages multiple threads");
+    let mut group2 = c.benchmark_group("Detector all languages multiple threads");
     group2.bench_function("all ngrams", |bencher| {
         bencher.iter(|| {
-            SENTENCES.par_iter().for_each(|sentence| {
-                black_box(detector_all_languages_all_ngrams.detect_top_one_raw(sentence));
-            });
+            black_box(detector_all_languages_all_ngrams.par_detect_top_one(SENTENCES));
         });
     });
     group2.bench_function("max trigrams", |bencher| {
         bencher.iter(|| {
-            SENTENCES.par_iter().for_each(|sentence| {
-                black_box(detector_all_languages_max_trigrams.detect_top_one_raw(sentence));
-            });
+            black_box(detector_all_languages_max_trigrams.par_detect_top_one(SENTENCES));
         });
     });
-    group2.finish(); */
+    group2.finish();
 
     let mut group_common = c.benchmark_group("Detector common languages");
 
@@ -120,24 +111,77 @@ fn benchmark_detector(c: &mut Criterion) {
     });
     group_common.finish();
 
-    /* let mut group4 = c.benchmark_group("Detector common languages multiple threads");
+    let mut group4 = c.benchmark_group("Detector common languages multiple threads");
     group4.bench_function("all ngrams", |bencher| {
         bencher.iter(|| {
-            SENTENCES.par_iter().for_each(|sentence| {
-                black_box(
-                    detector_common_languages_all_ngrams.detect_top_one_raw(sentence)),
-                );
-            });
+            black_box(detector_common_languages_all_ngrams.par_detect_top_one(SENTENCES));
         });
     });
     group4.bench_function("max trigrams", |bencher| {
         bencher.iter(|| {
-            SENTENCES.par_iter().for_each(|sentence| {
-                black_box(detector_common_languages_max_trigrams.detect_top_one_raw(sentence));
+            black_box(detector_common_languages_max_trigrams.par_detect_top_one(SENTENCES));
+        });
+    });
+    group4.finish();
+}
+
+fn benchmark_detector_long_texts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Detector long texts");
+
+    let models_storage = ModelsStorage::new().unwrap();
+    let detector = DetectorBuilder::new(&models_storage).build();
+    group.bench_function("all ngrams", |bencher| {
+        bencher.iter(|| {
+            LONG_TEXTS.iter().for_each(|text| {
+                black_box(detector.detect_top_one_raw(text));
+            });
+        });
+    });
+
+    let detector_early_exit = DetectorBuilder::new(&models_storage)
+        .early_exit(1.0)
+        .build();
+    group.bench_function("all ngrams early exit", |bencher| {
+        bencher.iter(|| {
+            LONG_TEXTS.iter().for_each(|text| {
+                black_box(detector_early_exit.detect_top_one_raw(text));
             });
         });
     });
-    group4.finish(); */
+    group.finish();
+}
+
+fn benchmark_detector_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Detector batch");
+
+    let models_storage = ModelsStorage::new().unwrap();
+    let detector = DetectorBuilder::new(&models_storage).build();
+    let mut scratch = DetectorScratch::new();
+    // warm up the scratch
+    SENTENCES.iter().for_each(|sentence| {
+        black_box(detector.probabilities_with_scratch(sentence, &mut scratch));
+    });
+
+    group.bench_function("probabilities", |bencher| {
+        bencher.iter(|| {
+            SENTENCES.iter().for_each(|sentence| {
+                black_box(detector.probabilities(sentence));
+            });
+        });
+    });
+    group.bench_function("probabilities with scratch", |bencher| {
+        bencher.iter(|| {
+            SENTENCES.iter().for_each(|sentence| {
+                black_box(detector.probabilities_with_scratch(sentence, &mut scratch));
+            });
+        });
+    });
+    group.bench_function("detect batch", |bencher| {
+        bencher.iter(|| {
+            black_box(detector.detect_batch(SENTENCES));
+        });
+    });
+    group.finish();
 }
 
 fn benchmark_new_all_languages(c: &mut Criterion) {
@@ -152,5 +196,11 @@ fn benchmark_new_all_languages(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, benchmark_detector, benchmark_new_all_languages,);
+criterion_group!(
+    benches,
+    benchmark_detector,
+    benchmark_detector_long_texts,
+    benchmark_detector_batch,
+    benchmark_new_all_languages,
+);
 criterion_main!(benches);
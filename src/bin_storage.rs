@@ -1,20 +1,186 @@
-use crate::{model::Model, ngram_size::NGRAM_MAX_LEN, NgramSize};
-use ::std::{collections::HashMap, fmt};
-use alphabet_detector::{IntoEnumIterator, ScriptLanguage, ScriptLanguageArr};
+use crate::{calibration::Calibration, model::Model, ngram_size::NGRAM_MAX_LEN, NgramSize};
+use ::std::{collections::HashMap, fmt, ops::Range};
+use alphabet_detector::{EnumCount, IntoEnumIterator, ScriptLanguage, ScriptLanguageArr};
 use debug_unsafe::slice::SliceGetter;
 use rkyv::util::AlignedVec;
+use rustc_hash::{FxHashMap, FxHashSet};
+use sha2::{Digest, Sha256};
 
-pub(crate) type StorageNgrams = HashMap<String, Vec<(u16, f64)>, rustc_hash::FxBuildHasher>;
+pub(crate) type StorageNgrams<P = f64> = HashMap<String, Vec<(u16, P)>, rustc_hash::FxBuildHasher>;
 // Vec because array requires 64-bit pointers, failed with
 // "out of range integral type conversion attempted"
-pub(crate) type StorageNgramsArr = Vec<StorageNgrams>;
+pub(crate) type StorageNgramsArr<P = f64> = Vec<StorageNgrams<P>>;
 
+/// Encoding of probabilities in the models binary
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ProbabilityEncoding {
+    /// Exact, 8 bytes per probability
+    #[default]
+    F64,
+    /// Quantized into 65536 buckets, 2 bytes per probability
+    U16,
+    /// Quantized into 256 buckets, 1 byte per probability
+    U8,
+}
+
+impl ProbabilityEncoding {
+    /// Dequantization table length
+    #[inline]
+    pub(crate) const fn buckets(self) -> usize {
+        match self {
+            Self::F64 => 0,
+            Self::U16 => 1 << 16,
+            Self::U8 => 1 << 8,
+        }
+    }
+}
+
+#[derive(Clone, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub(crate) struct QuantizedNgrams<Q> {
+    /// Dequantization table of logarithmic probabilities
+    pub(crate) table: Vec<f64>,
+    pub(crate) ngrams: StorageNgramsArr<Q>,
+    pub(crate) wordgrams: StorageNgrams<Q>,
+}
+
+/// Ngrams with quantized probabilities,
+/// which are used instead of `BinStorage::ngrams` and `BinStorage::wordgrams`
+#[derive(Clone, Default, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub(crate) enum Quantized {
+    #[default]
+    None,
+    U16(QuantizedNgrams<u16>),
+    U8(QuantizedNgrams<u8>),
+}
+
+/// Models binary starts with this magic, followed by the format version
+pub(crate) const MAGIC: [u8; 8] = *b"LANGRAM\0";
+/// Version of the models binary layout.
+/// Models binary without the header (`v0.11` models release) is the version 0.
+pub const FORMAT_VERSION: u32 = 1;
+/// SHA-256 of the archive, placed after magic, format version and a reserved `u32`
+pub(crate) const CHECKSUM_RANGE: Range<usize> = 16..48;
+/// Keeps the archive 16 bytes aligned
+pub(crate) const HEADER_LEN: usize = CHECKSUM_RANGE.end;
+
+/// SHA-256 of the archive (bytes after the header)
+#[inline]
+pub(crate) fn checksum(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes.get(HEADER_LEN..).unwrap_or_default()).into()
+}
+
+/// Distribution of logarithmic probabilities of a language model of a single ngram size,
+/// for ngrams sampled from the same model
+#[derive(Clone, Copy, Debug, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub(crate) struct NgramScore {
+    pub(crate) mean: f64,
+    pub(crate) deviation: f64,
+    /// Minimal probability of the model, negative infinity if there is no model
+    pub(crate) min: f64,
+}
+
+impl Default for NgramScore {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            mean: 0.0,
+            deviation: 0.0,
+            min: f64::NEG_INFINITY,
+        }
+    }
+}
+
+#[derive(Clone, Default, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub(crate) struct BinMetadata {
+    pub(crate) crate_version: String,
+    pub(crate) created_at: u64,
+    pub(crate) languages: Vec<u16>,
+    pub(crate) ngram_sizes: Vec<u8>,
+    pub(crate) corpus: String,
+}
+
+/// Information about the models binary
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelsMetadata {
+    pub format_version: u32,
+    /// Version of langram which created the models binary
+    pub crate_version: String,
+    /// Unix timestamp in seconds
+    pub created_at: u64,
+    /// Languages which have models
+    pub languages: Vec<ScriptLanguage>,
+    /// Ngram sizes which have models
+    pub ngram_sizes: Vec<NgramSize>,
+    /// Training corpus label
+    pub corpus: String,
+    pub encoding: ProbabilityEncoding,
+}
+
+impl ModelsMetadata {
+    pub(crate) fn new(
+        metadata: &ArchivedBinMetadata,
+        format_version: u32,
+        encoding: ProbabilityEncoding,
+    ) -> Self {
+        Self {
+            format_version,
+            crate_version: metadata.crate_version.to_string(),
+            created_at: metadata.created_at.to_native(),
+            languages: metadata
+                .languages
+                .iter()
+                .map(|l| l.to_native() as usize)
+                .filter(|&l| l < ScriptLanguage::COUNT)
+                .map(|l| unsafe { ScriptLanguage::transmute_from_usize(l) })
+                .collect(),
+            ngram_sizes: metadata
+                .ngram_sizes
+                .iter()
+                .map(|&s| s as usize)
+                .filter(|&s| s < NgramSize::COUNT)
+                .map(NgramSize::from)
+                .collect(),
+            corpus: metadata.corpus.to_string(),
+            encoding,
+        }
+    }
+}
+
+#[inline]
+fn unix_timestamp() -> u64 {
+    #[cfg(all(target_family = "wasm", target_os = "unknown"))]
+    return 0;
+    #[cfg(not(all(target_family = "wasm", target_os = "unknown")))]
+    ::std::time::SystemTime::now()
+        .duration_since(::std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Layout of the models binary without the header (`v0.11` models release)
 #[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub(crate) struct LegacyBinStorage {
+    pub(crate) langs_ngram_min_probability: ScriptLanguageArr<f64>,
+    pub(crate) ngrams: StorageNgramsArr,
+    pub(crate) wordgrams: StorageNgrams,
+    pub(crate) wordgram_min_probability: f64,
+    pub(crate) hash: u64,
+}
+
+#[derive(Clone, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 pub struct BinStorage {
     pub(crate) langs_ngram_min_probability: ScriptLanguageArr<f64>,
+    /// Subtracted from raw `langs_ngram_min_probability` on normalization,
+    /// so they can be renormalized after `overlay`
+    pub(crate) min_probability_shift: f64,
     pub(crate) ngrams: StorageNgramsArr,
     pub(crate) wordgrams: StorageNgrams,
     pub(crate) wordgram_min_probability: f64,
+    /// Indexed by language, then by `NgramSize`
+    pub(crate) langs_ngram_scores: ScriptLanguageArr<[NgramScore; NgramSize::COUNT]>,
+    pub(crate) quantized: Quantized,
+    pub(crate) calibration: Calibration,
+    pub(crate) metadata: BinMetadata,
     pub(crate) hash: u64,
 }
 
@@ -23,15 +189,38 @@ impl Default for BinStorage {
     fn default() -> Self {
         Self {
             langs_ngram_min_probability: ::core::array::from_fn(|_| f64::NEG_INFINITY),
+            min_probability_shift: 0.0,
             ngrams: vec![Default::default(); NGRAM_MAX_LEN],
             // can't be included in ngrams, requires 64-bit pointers
             wordgrams: Default::default(),
             wordgram_min_probability: Default::default(),
+            langs_ngram_scores: ::core::array::from_fn(|_| Default::default()),
+            quantized: Default::default(),
+            calibration: Default::default(),
+            metadata: Default::default(),
             hash: ScriptLanguage::HASH,
         }
     }
 }
 
+impl From<LegacyBinStorage> for BinStorage {
+    fn from(legacy: LegacyBinStorage) -> Self {
+        let mut bin_storage = Self {
+            langs_ngram_min_probability: legacy.langs_ngram_min_probability,
+            ngrams: legacy.ngrams,
+            wordgrams: legacy.wordgrams,
+            wordgram_min_probability: legacy.wordgram_min_probability,
+            hash: legacy.hash,
+            ..Default::default()
+        };
+        bin_storage.recover_min_probability_shift();
+        bin_storage.compute_ngram_scores();
+        bin_storage.update_metadata();
+
+        bin_storage
+    }
+}
+
 impl fmt::Debug for BinStorage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("FileStorage")
@@ -40,6 +229,8 @@ impl fmt::Debug for BinStorage {
                 &self.langs_ngram_min_probability,
             )
             .field("wordgram_min_probability", &self.wordgram_min_probability)
+            .field("encoding", &self.encoding())
+            .field("corpus", &self.metadata.corpus)
             .field("hash", &self.hash)
             .finish_non_exhaustive()
     }
@@ -83,12 +274,11 @@ impl BinStorage {
         }
     }
 
-    pub fn finalize(&mut self) {
-        // reorder
+    /// Sorts languages of each ngram
+    fn reorder(&mut self) {
         self.ngrams
             .iter_mut()
             .chain([&mut self.wordgrams])
-            .inspect(|v| println!("len {:?}", v.len()))
             .flat_map(|v| v.iter_mut())
             .for_each(|(_, v)| {
                 v.sort_by(|(l1, _), (l2, _)| unsafe {
@@ -96,22 +286,695 @@ impl BinStorage {
                         .cmp(&ScriptLanguage::transmute_from_usize(*l2 as usize))
                 })
             });
+    }
+
+    #[inline]
+    fn compute_wordgram_min_probability(&self) -> f64 {
+        self.wordgrams
+            .values()
+            .flat_map(|v| v.iter())
+            .fold(0.0, |acc, (_, prob)| acc.min(prob * 4.0))
+    }
+
+    /// Computes `langs_ngram_scores`.
+    ///
+    /// Stored probabilities of ngrams bigger than unigrams are conditional
+    /// (`freq / min(prefix_freq, suffix_freq)`), so they are not a distribution.
+    /// Ngrams are weighted by their frequencies, which are recovered from unigrams up.
+    fn compute_ngram_scores(&mut self) {
+        // sums of `freq`, `freq * ln(p)`, `freq * ln(p)^2`, and min `ln(p)`
+        let mut sums =
+            vec![[(0.0, 0.0, 0.0, f64::INFINITY); NgramSize::COUNT]; ScriptLanguage::COUNT];
+        let mut add = |lang: u16, ngram_size: usize, freq: f64, prob: f64| {
+            let sum = sums
+                .get_safe_unchecked_mut(lang as usize)
+                .get_safe_unchecked_mut(ngram_size);
+            sum.0 += freq;
+            sum.1 += freq * prob;
+            sum.2 += freq * prob * prob;
+            sum.3 = sum.3.min(prob);
+        };
+
+        // frequencies of the previous ngram size, relative to the unigrams count
+        let mut lower_freqs: FxHashMap<(&str, u16), f64> = FxHashMap::default();
+        for (ngram_size, ngram_model) in self.ngrams.iter().enumerate() {
+            let mut freqs = FxHashMap::default();
+            for (ngram, langs_probs) in ngram_model.iter() {
+                let prefix = &ngram[..ngram.char_indices().last().map_or(0, |(i, _)| i)];
+                let suffix = &ngram[ngram.chars().next().map_or(0, char::len_utf8)..];
+                for &(lang, prob) in langs_probs {
+                    let freq = if ngram_size == NgramSize::Uni as usize {
+                        prob.exp()
+                    } else {
+                        let lower_freq = |ngram| lower_freqs.get(&(ngram, lang)).copied();
+                        match (lower_freq(prefix), lower_freq(suffix)) {
+                            (Some(prefix_freq), Some(suffix_freq)) => {
+                                prob.exp() * prefix_freq.min(suffix_freq)
+                            }
+                            // frequency is unknown, only the min is affected
+                            _ => 0.0,
+                        }
+                    };
+                    freqs.insert((ngram.as_str(), lang), freq);
+                    add(lang, ngram_size, freq, prob);
+                }
+            }
+            lower_freqs = freqs;
+        }
+        drop(lower_freqs);
+        for &(lang, prob) in self.wordgrams.values().flat_map(|v| v.iter()) {
+            add(lang, NgramSize::Word as usize, prob.exp(), prob);
+        }
+
+        for (scores, sums) in self.langs_ngram_scores.iter_mut().zip(sums) {
+            for (score, (freq, freq_ln, freq_ln2, min)) in scores.iter_mut().zip(sums) {
+                *score = if freq > 0.0 {
+                    let mean = freq_ln / freq;
+                    NgramScore {
+                        mean,
+                        deviation: (freq_ln2 / freq - mean * mean).max(0.0).sqrt(),
+                        min,
+                    }
+                } else {
+                    Default::default()
+                };
+            }
+        }
+    }
+
+    /// Languages which have models
+    pub fn languages(&self) -> impl Iterator<Item = ScriptLanguage> + '_ {
+        ScriptLanguage::iter().filter(|&lang| {
+            self.langs_ngram_min_probability
+                .get_safe_unchecked(lang as usize)
+                .is_finite()
+        })
+    }
+
+    /// Replaces models of the languages contained in `other`.
+    ///
+    /// Min probabilities are renormalized, as if all models were added into one storage.
+    pub fn overlay(&mut self, other: BinStorage) {
+        let langs: FxHashSet<u16> = other.languages().map(|l| l as u16).collect();
+        if langs.is_empty() {
+            return;
+        }
+
+        for ngram_model in self.ngrams.iter_mut().chain([&mut self.wordgrams]) {
+            ngram_model.retain(|_, langs_probs| {
+                langs_probs.retain(|(l, _)| !langs.contains(l));
+                !langs_probs.is_empty()
+            });
+        }
+
+        let shift = other.min_probability_shift - self.min_probability_shift;
+        for &lang in langs.iter() {
+            *self
+                .langs_ngram_min_probability
+                .get_safe_unchecked_mut(lang as usize) = *other
+                .langs_ngram_min_probability
+                .get_safe_unchecked(lang as usize)
+                + shift;
+            // models of the language are not changed, so are their scores
+            *self
+                .langs_ngram_scores
+                .get_safe_unchecked_mut(lang as usize) =
+                *other.langs_ngram_scores.get_safe_unchecked(lang as usize);
+        }
+        self.normalize();
+
+        for (ngram_model, other_ngram_model) in self
+            .ngrams
+            .iter_mut()
+            .chain([&mut self.wordgrams])
+            .zip(other.ngrams.into_iter().chain([other.wordgrams]))
+        {
+            for (ngram, langs_probs) in other_ngram_model {
+                ngram_model.entry(ngram).or_default().extend(langs_probs);
+            }
+        }
+
+        self.wordgram_min_probability = self.compute_wordgram_min_probability();
+        self.reorder();
+        self.update_metadata();
+    }
+
+    /// Keeps only the selected languages and ngram sizes,
+    /// dropping ngrams that no retained language uses.
+    pub fn retain(
+        &mut self,
+        languages: impl IntoIterator<Item = ScriptLanguage>,
+        ngram_sizes: impl IntoIterator<Item = NgramSize>,
+    ) {
+        let langs: FxHashSet<u16> = languages.into_iter().map(|l| l as u16).collect();
+        let ngram_sizes: FxHashSet<NgramSize> = ngram_sizes.into_iter().collect();
+
+        for lang in ScriptLanguage::iter() {
+            if !langs.contains(&(lang as u16)) {
+                *self
+                    .langs_ngram_min_probability
+                    .get_safe_unchecked_mut(lang as usize) = f64::NEG_INFINITY;
+            }
+        }
+        // scores of the retained models are kept, as they can't be recomputed without lower ngram sizes
+        for (lang, scores) in self.langs_ngram_scores.iter_mut().enumerate() {
+            for (ngram_size, score) in scores.iter_mut().enumerate() {
+                if !langs.contains(&(lang as u16))
+                    || !ngram_sizes.contains(&NgramSize::from(ngram_size))
+                {
+                    *score = Default::default();
+                }
+            }
+        }
+
+        for (ngram_size, ngram_model) in self
+            .ngrams
+            .iter_mut()
+            .chain([&mut self.wordgrams])
+            .enumerate()
+        {
+            let ngram_size = if ngram_size < NGRAM_MAX_LEN {
+                NgramSize::from(ngram_size)
+            } else {
+                NgramSize::Word
+            };
+            if !ngram_sizes.contains(&ngram_size) {
+                *ngram_model = Default::default();
+                continue;
+            }
+
+            ngram_model.retain(|_, langs_probs| {
+                langs_probs.retain(|(l, _)| langs.contains(l));
+                langs_probs.shrink_to_fit();
+                !langs_probs.is_empty()
+            });
+            ngram_model.shrink_to_fit();
+        }
+
+        self.wordgram_min_probability = self.compute_wordgram_min_probability();
+        self.update_metadata();
+    }
+
+    /// Sets the calibration of relative probabilities (see [`Calibration::fit`]).
+    ///
+    /// Calibration is kept on `overlay` and `retain`, but it should be refitted.
+    #[inline]
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    #[inline]
+    pub fn calibration(&self) -> &Calibration {
+        &self.calibration
+    }
+
+    /// Sets the training corpus label
+    #[inline]
+    pub fn set_corpus(&mut self, corpus: impl Into<String>) {
+        self.metadata.corpus = corpus.into();
+    }
 
-        // normalize
+    /// Ngram sizes which have models
+    fn ngram_sizes(&self) -> impl Iterator<Item = NgramSize> + '_ {
+        NgramSize::iter().filter(|&ngram_size| {
+            let ngram_model = if ngram_size == NgramSize::Word {
+                &self.wordgrams
+            } else {
+                self.ngrams.get_safe_unchecked(ngram_size as usize)
+            };
+            !ngram_model.is_empty()
+        })
+    }
+
+    fn update_metadata(&mut self) {
+        self.metadata = BinMetadata {
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+            created_at: unix_timestamp(),
+            languages: self.languages().map(|l| l as u16).collect(),
+            ngram_sizes: self.ngram_sizes().map(|s| s as u8).collect(),
+            corpus: ::core::mem::take(&mut self.metadata.corpus),
+        };
+    }
+
+    /// Normalizes min probabilities by the max raw min probability of all languages
+    fn normalize(&mut self) {
         let max_prob = ScriptLanguage::iter().fold(f64::NEG_INFINITY, |acc, lang| {
             self.langs_ngram_min_probability
                 .get_safe_unchecked(lang as usize)
                 .max(acc)
-        }) + 0.05;
+        }) + self.min_probability_shift
+            + 0.05;
+        if !max_prob.is_finite() {
+            return;
+        }
+
+        let shift = max_prob - self.min_probability_shift;
         for lang in ScriptLanguage::iter() {
             *self
                 .langs_ngram_min_probability
-                .get_safe_unchecked_mut(lang as usize) -= max_prob;
+                .get_safe_unchecked_mut(lang as usize) -= shift;
+        }
+        self.min_probability_shift = max_prob;
+    }
+
+    /// Recovers `min_probability_shift` of normalized min probabilities,
+    /// from the unigrams count of a language, which it's raw min probability is computed from
+    fn recover_min_probability_shift(&mut self) {
+        let Some(lang) = self.languages().next() else {
+            return;
+        };
+        let unigrams_count = self
+            .ngrams
+            .get_safe_unchecked(NgramSize::Uni as usize)
+            .values()
+            .filter(|langs_probs| langs_probs.iter().any(|(l, _)| *l == lang as u16))
+            .count();
+        if unigrams_count == 0 {
+            return;
+        }
+
+        self.min_probability_shift = compute_min_probability(unigrams_count)
+            - self
+                .langs_ngram_min_probability
+                .get_safe_unchecked(lang as usize);
+    }
+
+    pub fn finalize(&mut self) {
+        self.reorder();
+        self.normalize();
+        self.compute_ngram_scores();
+        self.update_metadata();
+    }
+
+    #[inline]
+    pub fn encoding(&self) -> ProbabilityEncoding {
+        match self.quantized {
+            Quantized::None => ProbabilityEncoding::F64,
+            Quantized::U16(_) => ProbabilityEncoding::U16,
+            Quantized::U8(_) => ProbabilityEncoding::U8,
         }
     }
 
+    /// Quantizes probabilities uniformly between min and max,
+    /// each bucket is dequantized into the mean of it's probabilities
+    fn quantize<Q>(&self, buckets: usize, to_bucket: impl Fn(usize) -> Q) -> QuantizedNgrams<Q> {
+        let probabilities = || {
+            self.ngrams
+                .iter()
+                .chain([&self.wordgrams])
+                .flat_map(|ngram_model| ngram_model.values())
+                .flat_map(|langs_probs| langs_probs.iter().map(|(_, prob)| *prob))
+        };
+
+        let (min, max) = probabilities().fold((0.0_f64, f64::NEG_INFINITY), |(min, max), prob| {
+            (min.min(prob), max.max(prob))
+        });
+        let step = if max > min {
+            (max - min) / (buckets - 1) as f64
+        } else {
+            1.0
+        };
+        let bucket = |prob: f64| (((prob - min) / step).round() as usize).min(buckets - 1);
+
+        let mut sums = vec![(0.0, 0_usize); buckets];
+        for prob in probabilities() {
+            let sum = sums.get_safe_unchecked_mut(bucket(prob));
+            sum.0 += prob;
+            sum.1 += 1;
+        }
+        let table = sums
+            .into_iter()
+            .enumerate()
+            .map(|(i, (sum, cnt))| {
+                if cnt == 0 {
+                    min + step * i as f64
+                } else {
+                    sum / cnt as f64
+                }
+            })
+            .collect();
+
+        let quantize_model = |ngram_model: &StorageNgrams| -> StorageNgrams<Q> {
+            ngram_model
+                .iter()
+                .map(|(ngram, langs_probs)| {
+                    let langs_buckets = langs_probs
+                        .iter()
+                        .map(|&(lang, prob)| (lang, to_bucket(bucket(prob))))
+                        .collect();
+                    (ngram.clone(), langs_buckets)
+                })
+                .collect()
+        };
+
+        QuantizedNgrams {
+            table,
+            ngrams: self.ngrams.iter().map(quantize_model).collect(),
+            wordgrams: quantize_model(&self.wordgrams),
+        }
+    }
+
+    /// Converts quantized probabilities back into `f64`
+    pub(crate) fn dequantize(&mut self) {
+        match ::core::mem::take(&mut self.quantized) {
+            Quantized::None => {}
+            Quantized::U16(quantized) => self.dequantize_from(quantized),
+            Quantized::U8(quantized) => self.dequantize_from(quantized),
+        }
+    }
+
+    fn dequantize_from<Q: Copy + Into<usize>>(&mut self, quantized: QuantizedNgrams<Q>) {
+        let QuantizedNgrams {
+            table,
+            ngrams,
+            wordgrams,
+        } = quantized;
+
+        let dequantize_model = |ngram_model: StorageNgrams<Q>| -> StorageNgrams {
+            ngram_model
+                .into_iter()
+                .map(|(ngram, langs_buckets)| {
+                    let langs_probs = langs_buckets
+                        .into_iter()
+                        .map(|(lang, bucket)| (lang, *table.get_safe_unchecked(bucket.into())))
+                        .collect();
+                    (ngram, langs_probs)
+                })
+                .collect()
+        };
+
+        self.ngrams = ngrams.into_iter().map(dequantize_model).collect();
+        self.wordgrams = dequantize_model(wordgrams);
+    }
+
+    #[inline]
+    pub fn metadata(&self) -> ModelsMetadata {
+        ModelsMetadata {
+            format_version: FORMAT_VERSION,
+            crate_version: self.metadata.crate_version.clone(),
+            created_at: self.metadata.created_at,
+            languages: self.languages().collect(),
+            ngram_sizes: self.ngram_sizes().collect(),
+            corpus: self.metadata.corpus.clone(),
+            encoding: self.encoding(),
+        }
+    }
+
+    /// Serializes into the models binary: header, followed by the archive
     #[inline]
     pub fn to_bytes(&self) -> Result<AlignedVec, rkyv::rancor::Error> {
-        rkyv::to_bytes::<rkyv::rancor::Error>(self)
+        let mut bytes = AlignedVec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&[0; HEADER_LEN - MAGIC.len() - 4]);
+
+        let mut bytes = rkyv::api::high::to_bytes_in::<_, rkyv::rancor::Error>(self, bytes)?;
+        let checksum = checksum(&bytes);
+        bytes[CHECKSUM_RANGE].copy_from_slice(&checksum);
+
+        Ok(bytes)
+    }
+
+    /// Serializes with probabilities in the selected `encoding`.
+    /// Quantized encodings are smaller, but less accurate.
+    ///
+    /// Already quantized probabilities are dequantized first (in a copy).
+    pub fn to_bytes_encoded(
+        &self,
+        encoding: ProbabilityEncoding,
+    ) -> Result<AlignedVec, rkyv::rancor::Error> {
+        if encoding == self.encoding() {
+            return self.to_bytes();
+        }
+        if self.encoding() != ProbabilityEncoding::F64 {
+            let mut dequantized = self.clone();
+            dequantized.dequantize();
+            return dequantized.to_bytes_encoded(encoding);
+        }
+
+        let buckets = encoding.buckets();
+        let quantized = match encoding {
+            ProbabilityEncoding::F64 => return self.to_bytes(),
+            ProbabilityEncoding::U16 => Quantized::U16(self.quantize(buckets, |b| b as u16)),
+            ProbabilityEncoding::U8 => Quantized::U8(self.quantize(buckets, |b| b as u8)),
+        };
+
+        let quantized_storage = BinStorage {
+            langs_ngram_min_probability: self.langs_ngram_min_probability,
+            min_probability_shift: self.min_probability_shift,
+            ngrams: vec![Default::default(); NGRAM_MAX_LEN],
+            wordgrams: Default::default(),
+            wordgram_min_probability: self.wordgram_min_probability,
+            langs_ngram_scores: self.langs_ngram_scores,
+            quantized,
+            calibration: self.calibration.clone(),
+            metadata: self.metadata.clone(),
+            hash: self.hash,
+        };
+
+        quantized_storage.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BinStorage, ProbabilityEncoding, Quantized};
+    use crate::{
+        model::Model, ngram_size::NGRAM_MAX_LEN, DetectorBuilder, ModelsStorage, NgramSize,
+        ScriptLanguage,
+    };
+
+    fn model(unigrams: &[(&str, f64)], wordgrams: &[(&str, f64)]) -> Model {
+        let mut model = Model::default();
+        model[NgramSize::Uni as usize] = unigrams
+            .iter()
+            .map(|&(ngram, prob)| (ngram.to_owned(), prob.ln()))
+            .collect();
+        model[NgramSize::Word as usize] = wordgrams
+            .iter()
+            .map(|&(ngram, prob)| (ngram.to_owned(), prob.ln()))
+            .collect();
+        model
+    }
+
+    #[test]
+    fn test_overlay() {
+        let mut base = BinStorage::default();
+        base.add(
+            ScriptLanguage::English,
+            model(&[("a", 0.5), ("b", 0.5)], &[("ab", 0.001)]),
+        );
+        base.add(ScriptLanguage::German, model(&[("a", 0.3)], &[("a", 0.5)]));
+        base.finalize();
+
+        let mut overlay = BinStorage::default();
+        overlay.add(ScriptLanguage::English, model(&[("c", 1.0)], &[("c", 0.5)]));
+        overlay.finalize();
+
+        base.overlay(overlay);
+
+        assert_eq!(
+            base.languages().collect::<Vec<_>>(),
+            [ScriptLanguage::English, ScriptLanguage::German]
+        );
+        let unigrams = &base.ngrams[NgramSize::Uni as usize];
+        assert_eq!(
+            unigrams["a"],
+            [(ScriptLanguage::German as u16, 0.3_f64.ln())]
+        );
+        assert!(!unigrams.contains_key("b"));
+        assert_eq!(unigrams["c"], [(ScriptLanguage::English as u16, 0.0)]);
+        assert!(!base.wordgrams.contains_key("ab"));
+        assert_eq!(base.wordgram_min_probability, 0.5_f64.ln() * 4.0);
+    }
+
+    #[test]
+    fn test_overlay_identical() {
+        let english = || model(&[("a", 0.5), ("b", 0.5)], &[("ab", 0.001)]);
+        let mut base = BinStorage::default();
+        base.add(ScriptLanguage::English, english());
+        base.add(ScriptLanguage::German, model(&[("c", 0.3)], &[("a", 0.5)]));
+        base.finalize();
+        let base_bytes = base.to_bytes().unwrap();
+        let base_min_probability = base.langs_ngram_min_probability;
+
+        let mut overlay = BinStorage::default();
+        overlay.add(ScriptLanguage::English, english());
+        overlay.finalize();
+        base.overlay(overlay);
+
+        let base_storage = ModelsStorage::from_vec(base_bytes).unwrap();
+        let overlaid_storage = ModelsStorage::from_vec(base.to_bytes().unwrap()).unwrap();
+        assert_eq!(base.langs_ngram_min_probability, base_min_probability);
+
+        let languages = [ScriptLanguage::English, ScriptLanguage::German];
+        let base_detector = DetectorBuilder::new(&base_storage)
+            .languages(languages)
+            .build();
+        let overlaid_detector = DetectorBuilder::new(&overlaid_storage)
+            .languages(languages)
+            .build();
+        for text in ["a", "ab", "c", "ab abc d"] {
+            assert_eq!(
+                base_detector.probabilities(text),
+                overlaid_detector.probabilities(text),
+                "{text}"
+            );
+            assert_eq!(
+                base_detector.explain(text, languages[0], languages[1]),
+                overlaid_detector.explain(text, languages[0], languages[1]),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_ngram_scores() {
+        // trained on words "ab", "ab", "aa"
+        let mut model = model(&[("a", 4.0 / 6.0), ("b", 2.0 / 6.0)], &[]);
+        model[NgramSize::Bi as usize] = [("ab", 2.0 / 2.0), ("aa", 1.0 / 4.0)]
+            .into_iter()
+            .map(|(ngram, prob): (&str, f64)| (ngram.to_owned(), prob.ln()))
+            .collect();
+        let mut storage = BinStorage::default();
+        storage.add(ScriptLanguage::English, model);
+        storage.finalize();
+
+        // bigrams of the words, not of the stored probabilities
+        let expected_mean = (2.0 * 1.0_f64.ln() + 0.25_f64.ln()) / 3.0;
+        let expected_deviation = 0.25_f64.ln().abs() * 2.0_f64.sqrt() / 3.0;
+        let score =
+            storage.langs_ngram_scores[ScriptLanguage::English as usize][NgramSize::Bi as usize];
+        assert!((score.mean - expected_mean).abs() < 1e-9, "{score:?}");
+        assert!(
+            (score.deviation - expected_deviation).abs() < 1e-9,
+            "{score:?}"
+        );
+        assert_eq!(score.min, 0.25_f64.ln());
+
+        // unigrams are required to recompute, so scores are kept
+        storage.retain([ScriptLanguage::English], [NgramSize::Bi]);
+        let scores = storage.langs_ngram_scores[ScriptLanguage::English as usize];
+        assert_eq!(scores[NgramSize::Bi as usize], score);
+        assert_eq!(scores[NgramSize::Uni as usize].min, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut storage = BinStorage::default();
+        storage.add(
+            ScriptLanguage::English,
+            model(&[("a", 0.5), ("b", 0.5)], &[("ab", 0.001)]),
+        );
+        storage.add(ScriptLanguage::German, model(&[("a", 0.3)], &[("a", 0.5)]));
+        storage.finalize();
+
+        storage.retain([ScriptLanguage::German], [NgramSize::Uni]);
+
+        assert_eq!(
+            storage.languages().collect::<Vec<_>>(),
+            [ScriptLanguage::German]
+        );
+        let unigrams = &storage.ngrams[NgramSize::Uni as usize];
+        assert_eq!(unigrams.len(), 1);
+        assert_eq!(
+            unigrams["a"],
+            [(ScriptLanguage::German as u16, 0.3_f64.ln())]
+        );
+        assert!(storage.wordgrams.is_empty());
+        assert_eq!(storage.wordgram_min_probability, 0.0);
+    }
+
+    #[test]
+    fn test_quantize() {
+        let mut storage = BinStorage::default();
+        storage.add(
+            ScriptLanguage::English,
+            model(&[("a", 0.5), ("b", 0.01)], &[("ab", 0.001)]),
+        );
+        storage.add(ScriptLanguage::German, model(&[("a", 0.3)], &[("a", 0.5)]));
+        storage.finalize();
+
+        let step = 0.001_f64.ln() / 255.0;
+        for (encoding, max_error) in [
+            (ProbabilityEncoding::U16, f64::EPSILON),
+            (ProbabilityEncoding::U8, step.abs()),
+        ] {
+            let bytes = storage.to_bytes_encoded(encoding).unwrap();
+            let models_storage = ModelsStorage::from_vec(bytes).unwrap();
+            assert_eq!(models_storage.encoding(), encoding);
+
+            let dequantized = models_storage.to_bin_storage().unwrap();
+            assert_eq!(dequantized.encoding(), ProbabilityEncoding::F64);
+            for (ngram_model, dequantized_model) in storage
+                .ngrams
+                .iter()
+                .chain([&storage.wordgrams])
+                .zip(dequantized.ngrams.iter().chain([&dequantized.wordgrams]))
+            {
+                assert_eq!(ngram_model.len(), dequantized_model.len());
+                for (ngram, langs_probs) in ngram_model {
+                    for (&(lang, prob), &(dequantized_lang, dequantized_prob)) in
+                        langs_probs.iter().zip(dequantized_model[ngram].iter())
+                    {
+                        assert_eq!(lang, dequantized_lang);
+                        assert!(
+                            (prob - dequantized_prob).abs() <= max_error,
+                            "{encoding:?} {ngram} {prob} {dequantized_prob}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_quantize_quantized() {
+        let mut storage = BinStorage::default();
+        storage.add(
+            ScriptLanguage::English,
+            model(&[("a", 0.5), ("b", 0.01)], &[("ab", 0.001)]),
+        );
+        storage.add(ScriptLanguage::German, model(&[("a", 0.3)], &[("a", 0.5)]));
+        storage.finalize();
+
+        let mut quantized = storage.clone();
+        quantized.quantized =
+            Quantized::U8(storage.quantize(ProbabilityEncoding::U8.buckets(), |b| b as u8));
+        quantized.ngrams = vec![Default::default(); NGRAM_MAX_LEN];
+        quantized.wordgrams = Default::default();
+        let mut dequantized = quantized.clone();
+        dequantized.dequantize();
+
+        for encoding in [
+            ProbabilityEncoding::F64,
+            ProbabilityEncoding::U16,
+            ProbabilityEncoding::U8,
+        ] {
+            let bytes = quantized.to_bytes_encoded(encoding).unwrap();
+            let models_storage = ModelsStorage::from_vec(bytes).unwrap();
+            assert_eq!(models_storage.encoding(), encoding);
+
+            let reencoded = models_storage.to_bin_storage().unwrap();
+            for (ngram_model, reencoded_model) in dequantized
+                .ngrams
+                .iter()
+                .chain([&dequantized.wordgrams])
+                .zip(reencoded.ngrams.iter().chain([&reencoded.wordgrams]))
+            {
+                assert_eq!(ngram_model.len(), reencoded_model.len());
+                for (ngram, langs_probs) in ngram_model {
+                    for (&(lang, prob), &(reencoded_lang, reencoded_prob)) in
+                        langs_probs.iter().zip(reencoded_model[ngram].iter())
+                    {
+                        assert_eq!(lang, reencoded_lang);
+                        assert!(
+                            (prob - reencoded_prob).abs() <= f64::EPSILON,
+                            "{encoding:?} {ngram} {prob} {reencoded_prob}"
+                        );
+                    }
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,246 @@
+use alphabet_detector::ScriptLanguage;
+
+/// Range of `ln(temperature)`, searched by [`Calibration::fit`]
+const LN_TEMPERATURE_RANGE: (f64, f64) = (-7.0, 5.0);
+const FIT_ITERATIONS: usize = 100;
+
+/// Temperature of logarithmic probabilities,
+/// for texts with at least `min_characters` characters
+#[derive(Clone, Copy, Debug, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub struct CalibrationBucket {
+    pub min_characters: u32,
+    pub temperature: f64,
+}
+
+/// Temperature scaling of relative probabilities, fitted per text length bucket,
+/// so calibrated probabilities can be compared across text lengths.
+///
+/// Empty if not calibrated, then the temperature is 1.0.
+#[derive(Clone, Debug, Default, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub struct Calibration {
+    /// Sorted by `min_characters`
+    buckets: Vec<CalibrationBucket>,
+}
+
+/// Detection result of a labelled text, used to fit [`Calibration`]
+#[derive(Clone, Debug)]
+pub struct CalibrationSample {
+    /// Characters count of the detected words
+    pub characters_count: usize,
+    /// Logarithmic probabilities, as returned by `Detector::probabilities`
+    pub probabilities: Vec<(ScriptLanguage, f64)>,
+    /// Correct language of the text
+    pub language: ScriptLanguage,
+}
+
+impl CalibrationSample {
+    /// Negative log-likelihood of the correct language,
+    /// with probabilities scaled by `temperature`
+    fn loss(&self, temperature: f64) -> f64 {
+        let correct = self
+            .probabilities
+            .iter()
+            .find(|(l, _)| *l == self.language)
+            .map_or(f64::NEG_INFINITY, |(_, p)| p * temperature);
+
+        let max = self
+            .probabilities
+            .iter()
+            .map(|(_, p)| p * temperature)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = self
+            .probabilities
+            .iter()
+            .map(|(_, p)| (p * temperature - max).exp())
+            .sum();
+
+        max + sum.ln() - correct
+    }
+
+    /// Only samples, where the correct language is compared with others, can be calibrated
+    fn is_calibratable(&self) -> bool {
+        self.probabilities.len() > 1
+            && self
+                .probabilities
+                .iter()
+                .any(|(l, p)| *l == self.language && p.is_finite())
+    }
+}
+
+impl Calibration {
+    pub fn new(buckets: impl IntoIterator<Item = CalibrationBucket>) -> Self {
+        let mut buckets: Vec<_> = buckets.into_iter().collect();
+        buckets.sort_unstable_by_key(|b| b.min_characters);
+
+        Self { buckets }
+    }
+
+    #[inline]
+    pub fn buckets(&self) -> &[CalibrationBucket] {
+        &self.buckets
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Fits a temperature for each bucket, starting at `min_characters`,
+    /// by minimizing the negative log-likelihood of the correct languages.
+    ///
+    /// Buckets without calibratable samples have the temperature 1.0.
+    pub fn fit(
+        min_characters: impl IntoIterator<Item = u32>,
+        samples: impl IntoIterator<Item = CalibrationSample>,
+    ) -> Self {
+        let mut calibration =
+            Self::new(
+                min_characters
+                    .into_iter()
+                    .map(|min_characters| CalibrationBucket {
+                        min_characters,
+                        temperature: 1.0,
+                    }),
+            );
+        calibration.buckets.dedup_by_key(|b| b.min_characters);
+
+        let mut buckets_samples: Vec<Vec<CalibrationSample>> =
+            vec![Vec::new(); calibration.buckets.len()];
+        for sample in samples.into_iter().filter(|s| s.is_calibratable()) {
+            if let Some(i) = calibration.bucket_index(sample.characters_count) {
+                buckets_samples[i].push(sample);
+            }
+        }
+
+        for (bucket, samples) in calibration.buckets.iter_mut().zip(buckets_samples) {
+            if !samples.is_empty() {
+                bucket.temperature = fit_temperature(&samples);
+            }
+        }
+
+        calibration
+    }
+
+    #[inline]
+    fn bucket_index(&self, characters_count: usize) -> Option<usize> {
+        self.buckets
+            .partition_point(|b| b.min_characters as usize <= characters_count)
+            .checked_sub(1)
+    }
+
+    /// Temperature for a text with `characters_count`
+    #[inline]
+    pub fn temperature(&self, characters_count: usize) -> f64 {
+        self.bucket_index(characters_count)
+            .map_or(1.0, |i| self.buckets[i].temperature)
+    }
+}
+
+impl ArchivedCalibration {
+    /// Temperature for a text with `characters_count`
+    #[inline]
+    pub(crate) fn temperature(&self, characters_count: usize) -> f64 {
+        let i = self
+            .buckets
+            .partition_point(|b| b.min_characters.to_native() as usize <= characters_count);
+        i.checked_sub(1)
+            .map_or(1.0, |i| self.buckets[i].temperature.to_native())
+    }
+
+    pub(crate) fn to_native(&self) -> Calibration {
+        Calibration {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|b| CalibrationBucket {
+                    min_characters: b.min_characters.to_native(),
+                    temperature: b.temperature.to_native(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Golden-section search of `ln(temperature)`, the loss is convex in temperature
+fn fit_temperature(samples: &[CalibrationSample]) -> f64 {
+    let loss = |ln_temperature: f64| -> f64 {
+        let temperature = ln_temperature.exp();
+        samples.iter().map(|s| s.loss(temperature)).sum()
+    };
+
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut a, mut b) = LN_TEMPERATURE_RANGE;
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    let (mut loss_c, mut loss_d) = (loss(c), loss(d));
+    for _ in 0..FIT_ITERATIONS {
+        if loss_c < loss_d {
+            b = d;
+            d = c;
+            loss_d = loss_c;
+            c = b - ratio * (b - a);
+            loss_c = loss(c);
+        } else {
+            a = c;
+            c = d;
+            loss_c = loss_d;
+            d = a + ratio * (b - a);
+            loss_d = loss(d);
+        }
+    }
+
+    ((a + b) / 2.0).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Calibration, CalibrationBucket, CalibrationSample};
+    use alphabet_detector::ScriptLanguage;
+
+    fn sample(probabilities: [f64; 2], correct: usize) -> CalibrationSample {
+        let languages = [ScriptLanguage::English, ScriptLanguage::German];
+        CalibrationSample {
+            characters_count: 10,
+            probabilities: languages.into_iter().zip(probabilities).collect(),
+            language: languages[correct],
+        }
+    }
+
+    #[test]
+    fn test_temperature() {
+        let calibration = Calibration::new([
+            CalibrationBucket {
+                min_characters: 50,
+                temperature: 4.0,
+            },
+            CalibrationBucket {
+                min_characters: 10,
+                temperature: 2.0,
+            },
+        ]);
+        assert_eq!(calibration.temperature(0), 1.0);
+        assert_eq!(calibration.temperature(10), 2.0);
+        assert_eq!(calibration.temperature(49), 2.0);
+        assert_eq!(calibration.temperature(1000), 4.0);
+        assert_eq!(Calibration::default().temperature(10), 1.0);
+    }
+
+    #[test]
+    fn test_fit() {
+        // difference of 1.0 is right in 3 of 4 samples,
+        // so the calibrated probability is 0.75, temperature is ln(3)
+        let samples = [
+            sample([-1.0, -2.0], 0),
+            sample([-1.0, -2.0], 0),
+            sample([-1.0, -2.0], 0),
+            sample([-1.0, -2.0], 1),
+            // not calibratable
+            sample([-1.0, f64::NEG_INFINITY], 1),
+        ];
+        let calibration = Calibration::fit([0, 100, 0], samples);
+
+        assert_eq!(calibration.buckets().len(), 2);
+        assert!((calibration.temperature(10) - 3f64.ln()).abs() < 1e-6);
+        assert_eq!(calibration.temperature(100), 1.0);
+    }
+}
@@ -1,6 +1,7 @@
-use super::{Detector, ModelsStorage, NgramSize};
+use super::{Detector, LanguageFilter, ModelsStorage, NgramSize};
 use crate::ngram_size::{NgramSizes, NgramSizesTrait};
 use alphabet_detector::{IntoEnumIterator, ScriptLanguage, ScriptLanguageIter};
+use rustc_hash::FxHashMap;
 
 #[derive(Clone, Debug)]
 pub struct DetectorBuilder<'m, L>
@@ -12,6 +13,10 @@ where
     pub(super) long_text_minlen: usize,
     pub(super) long_text_ngram_sizes: NgramSizes,
     pub(super) short_text_ngram_sizes: NgramSizes,
+    pub(super) early_exit_distance: Option<f64>,
+    pub(super) priors: FxHashMap<ScriptLanguage, f64>,
+    pub(super) included: Option<Vec<LanguageFilter>>,
+    pub(super) excluded: Vec<LanguageFilter>,
 }
 
 impl<'m> DetectorBuilder<'m, ScriptLanguageIter> {
@@ -24,6 +29,10 @@ impl<'m> DetectorBuilder<'m, ScriptLanguageIter> {
             long_text_minlen: 120,
             long_text_ngram_sizes: NgramSizes::new_const(),
             short_text_ngram_sizes: NgramSizes::new_const(),
+            early_exit_distance: None,
+            priors: Default::default(),
+            included: None,
+            excluded: Vec::new(),
         }
     }
 }
@@ -49,9 +58,37 @@ where
             long_text_minlen: self.long_text_minlen,
             long_text_ngram_sizes: self.long_text_ngram_sizes,
             short_text_ngram_sizes: self.short_text_ngram_sizes,
+            early_exit_distance: self.early_exit_distance,
+            priors: self.priors,
+            included: self.included,
+            excluded: self.excluded,
         }
     }
 
+    /// Keep only languages matching any of the `filters`
+    /// (e.g. [`UcdScript::Latin`](alphabet_detector::UcdScript::Latin) for all Latin script languages).
+    /// Can be called multiple times to extend the selection.
+    #[inline]
+    pub fn include<F: Into<LanguageFilter>>(
+        mut self,
+        filters: impl IntoIterator<Item = F>,
+    ) -> Self {
+        self.included
+            .get_or_insert_with(Vec::new)
+            .extend(filters.into_iter().map(Into::into));
+        self
+    }
+
+    /// Remove languages matching any of the `filters`, applied after [`include`](Self::include)
+    #[inline]
+    pub fn exclude<F: Into<LanguageFilter>>(
+        mut self,
+        filters: impl IntoIterator<Item = F>,
+    ) -> Self {
+        self.excluded.extend(filters.into_iter().map(Into::into));
+        self
+    }
+
     /// Min text length (in chars, excluding word separators) for
     /// switching from short ngrams to long ngrams
     #[inline]
@@ -88,6 +125,28 @@ where
         self
     }
 
+    /// Stop scoring the text, when the distance between the first and the second
+    /// logarithmic probabilities reaches `distance` (checked every 16 words).
+    /// Faster for long texts, but the result is computed only for the consumed part of the text
+    /// (see [`DetectionResult::consumed_words`](super::DetectionResult::consumed_words)).
+    #[inline]
+    pub fn early_exit(mut self, distance: f64) -> Self {
+        debug_assert!(distance >= 0.0, "Early exit distance must be >= 0.0");
+        self.early_exit_distance = Some(distance);
+        self
+    }
+
+    /// Logarithmic priors of languages (0.0 for not provided languages),
+    /// which reflect the expected languages mix of the texts.
+    ///
+    /// A prior is added to the sum of ngrams logarithmic probabilities of the language,
+    /// so it affects short texts more than long texts.
+    #[inline]
+    pub fn priors(mut self, priors: impl IntoIterator<Item = (ScriptLanguage, f64)>) -> Self {
+        self.priors = priors.into_iter().collect();
+        self
+    }
+
     /// Faster, but lower accuracy
     #[inline]
     pub fn max_trigrams(mut self) -> Self {
@@ -0,0 +1,46 @@
+use alphabet_detector::ScriptLanguage;
+use rustc_hash::FxHashMap;
+
+/// Context of a text known before the detection (e.g. of its document or author),
+/// used by [`Detector::probabilities_with_context`](super::Detector::probabilities_with_context).
+///
+/// Logarithmic priors of the context are added to the detector priors
+/// (see [`DetectorBuilder::priors`](super::DetectorBuilder::priors)),
+/// so they matter most for short texts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Context {
+    /// Logarithmic priors of languages, 0.0 for not provided languages
+    pub priors: FxHashMap<ScriptLanguage, f64>,
+}
+
+impl Context {
+    /// Context with logarithmic priors of languages
+    #[inline]
+    pub fn with_priors(priors: impl IntoIterator<Item = (ScriptLanguage, f64)>) -> Self {
+        Self {
+            priors: priors.into_iter().collect(),
+        }
+    }
+
+    /// Context with languages of the previous texts (e.g. of the same author).
+    /// The logarithmic prior of a language is `ln(1 + count)`,
+    /// where `count` is the count of previous texts in the language.
+    pub fn with_previous_languages(languages: impl IntoIterator<Item = ScriptLanguage>) -> Self {
+        let mut counts = FxHashMap::<ScriptLanguage, usize>::default();
+        for language in languages {
+            *counts.entry(language).or_default() += 1;
+        }
+
+        Self::with_priors(
+            counts
+                .into_iter()
+                .map(|(language, count)| (language, (count as f64).ln_1p())),
+        )
+    }
+
+    /// Logarithmic prior of the language
+    #[inline]
+    pub fn prior(&self, language: ScriptLanguage) -> f64 {
+        self.priors.get(&language).copied().unwrap_or_default()
+    }
+}
@@ -0,0 +1,250 @@
+use super::{
+    storage::{NgramModel, NgramModelFn, Probability},
+    Detector,
+};
+use crate::{ngram_size::NgramSize, ngrams::ngram_iterator};
+use ::std::{borrow::Borrow, fmt};
+use alphabet_detector::{fulltext_filter_with_margin, ScriptLanguage};
+use debug_unsafe::slice::SliceGetter;
+use rkyv::{tuple::ArchivedTuple2, Archived};
+use rustc_hash::FxHashSet;
+
+/// Logarithmic probability of an ngram for a language
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NgramProbability {
+    /// Ngram is found in the language model, counted in the mean
+    Found(f64),
+    /// Ngram is found only in other languages models,
+    /// the language minimum probability is added, but not counted in the mean
+    MinProbability(f64),
+}
+
+impl NgramProbability {
+    #[inline]
+    pub fn value(self) -> f64 {
+        match self {
+            Self::Found(p) | Self::MinProbability(p) => p,
+        }
+    }
+}
+
+/// Single ngram of the text, with probabilities of both compared languages
+#[derive(Clone, Debug, PartialEq)]
+pub struct NgramExplanation {
+    pub ngram: String,
+    pub probabilities: [NgramProbability; 2],
+    /// Sums of logarithmic probabilities of both languages, including this ngram
+    pub cumulative: [f64; 2],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NgramSizeExplanation {
+    pub ngram_size: NgramSize,
+    /// Ngrams found in at least one of the detected languages models, in the text order.
+    /// Other ngrams don't affect probabilities.
+    pub ngrams: Vec<NgramExplanation>,
+}
+
+/// Explanation of the difference between probabilities of two languages,
+/// returned by [`Detector::explain`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explanation {
+    pub languages: [ScriptLanguage; 2],
+    pub ngram_sizes: Vec<NgramSizeExplanation>,
+    /// Sums of logarithmic probabilities, and counts of found ngrams of both languages
+    pub totals: [(f64, usize); 2],
+    /// Logarithmic priors of both languages (see [`DetectorBuilder::priors`](super::DetectorBuilder::priors)),
+    /// added to the sums
+    pub priors: [f64; 2],
+}
+
+impl Explanation {
+    /// Mean logarithmic probabilities of both languages, as in [`Detector::probabilities`].
+    ///
+    /// All ngrams of the text are counted, so they differ if
+    /// [`DetectorBuilder::early_exit`](super::DetectorBuilder::early_exit) stopped the detection earlier.
+    pub fn probabilities(&self) -> [f64; 2] {
+        let mut res = [0.0; 2];
+        for ((r, (p, cnt)), prior) in res.iter_mut().zip(self.totals).zip(self.priors) {
+            *r = if cnt == 0 {
+                f64::NEG_INFINITY
+            } else {
+                (p + prior) / cnt as f64
+            };
+        }
+        res
+    }
+}
+
+impl fmt::Display for NgramProbability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Found(p) => write!(f, "{p:>9.4}"),
+            Self::MinProbability(p) => write!(f, "{p:>9.4} (min)"),
+        }
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b] = self.languages;
+        for NgramSizeExplanation { ngram_size, ngrams } in &self.ngram_sizes {
+            writeln!(f, "{ngram_size:?}:")?;
+            for ngram in ngrams {
+                let [pa, pb] = ngram.probabilities;
+                let [ca, cb] = ngram.cumulative;
+                writeln!(
+                    f,
+                    "  {:<12} {a:?} {pa:<15} {b:?} {pb:<15} cumulative {ca:.4} {cb:.4}",
+                    format!("{:?}", ngram.ngram),
+                )?;
+            }
+        }
+
+        let [(sa, ca), (sb, cb)] = self.totals;
+        let [ra, rb] = self.priors;
+        let [pa, pb] = self.probabilities();
+        write!(
+            f,
+            "Total: {a:?} {pa:.4} ({sa:.4} / {ca}, prior {ra:.4}), {b:?} {pb:.4} ({sb:.4} / {cb}, prior {rb:.4})"
+        )
+    }
+}
+
+/// Probabilities of `targets` for `ngram`, as computed in `NgramsSumCnt`.
+/// [`None`] if the ngram is not found in any of `languages`.
+struct NgramProbabilities<'a> {
+    ngram: &'a str,
+    languages: &'a FxHashSet<ScriptLanguage>,
+    targets: [(ScriptLanguage, f64); 2],
+}
+
+impl NgramModelFn for NgramProbabilities<'_> {
+    type Output = Option<[NgramProbability; 2]>;
+
+    fn call<P: Probability>(
+        self,
+        ngram_model: &NgramModel<P>,
+        table: &[Archived<f64>],
+    ) -> Option<[NgramProbability; 2]> {
+        let Self {
+            ngram,
+            languages,
+            targets,
+        } = self;
+        let langs_probs = ngram_model.get(ngram)?;
+
+        let mut found = false;
+        let mut probabilities = targets.map(|(_, min)| NgramProbability::MinProbability(min));
+        for ArchivedTuple2(language, prob) in langs_probs.iter() {
+            let language =
+                unsafe { ScriptLanguage::transmute_from_usize(language.to_native() as usize) };
+            if !languages.contains(&language) {
+                continue;
+            }
+            found = true;
+
+            for (probability, (target, _)) in probabilities.iter_mut().zip(targets) {
+                if language == target {
+                    *probability = NgramProbability::Found(P::dequantize(prob, table));
+                }
+            }
+        }
+
+        found.then_some(probabilities)
+    }
+}
+
+impl Detector<'_> {
+    /// Explains the difference between probabilities of languages `a` and `b`:
+    /// lists ngrams of the text for each used `NgramSize`,
+    /// with their logarithmic probabilities, and cumulative sums.
+    ///
+    /// Both languages are compared, even if not selected or not detected by `alphabet_detector`.
+    /// Early exit is not applied, see [`Explanation::probabilities`].
+    pub fn explain(&self, text: &str, a: ScriptLanguage, b: ScriptLanguage) -> Explanation {
+        let (words, langs, _) = fulltext_filter_with_margin::<Vec<char>, 95>(text.char_indices());
+        let mut filtered_languages: FxHashSet<_> = langs
+            .filter(|(l, _)| self.languages.contains(l))
+            .map(|(l, _)| l)
+            .collect();
+        filtered_languages.extend([a, b]);
+
+        let characters_count: usize = words.iter().map(|wd| wd.buf.len()).sum();
+        let ngram_sizes = if characters_count >= self.long_text_minlen {
+            &self.long_text_ngram_sizes
+        } else {
+            &self.short_text_ngram_sizes
+        };
+
+        let models_storage = self.models_storage;
+        let targets = |ngram_size: NgramSize| {
+            [a, b].map(|language| {
+                let min_probability = if ngram_size == NgramSize::Word {
+                    models_storage.wordgram_min_probability
+                } else {
+                    models_storage
+                        .langs_ngram_min_probability
+                        .get_safe_unchecked(language as usize)
+                        .to_native()
+                };
+                (language, min_probability)
+            })
+        };
+        let lookup = |ngram_size: NgramSize, ngram: &str| {
+            let languages = &filtered_languages;
+            let targets = targets(ngram_size);
+            models_storage.with_ngram_model(
+                ngram_size,
+                NgramProbabilities {
+                    ngram,
+                    languages,
+                    targets,
+                },
+            )
+        };
+
+        let mut explanation = Explanation {
+            languages: [a, b],
+            ngram_sizes: Vec::new(),
+            totals: Default::default(),
+            priors: [a, b].map(|language| self.priors.get(&language).copied().unwrap_or_default()),
+        };
+        let mut totals: [(f64, usize); 2] = Default::default();
+        for &ngram_size in ngram_sizes.iter() {
+            let ngrams: Vec<String> = if ngram_size == NgramSize::Word {
+                words.iter().map(|wd| wd.buf.iter().collect()).collect()
+            } else {
+                ngram_iterator(words.iter().map(|wd| wd.buf.as_ref()), ngram_size)
+                    .map(|ngram| Borrow::<str>::borrow(&ngram).to_owned())
+                    .collect()
+            };
+
+            let ngrams = ngrams
+                .into_iter()
+                .filter_map(|ngram| {
+                    let probabilities = lookup(ngram_size, &ngram)?;
+                    for ((sum, cnt), probability) in totals.iter_mut().zip(probabilities) {
+                        *sum += probability.value();
+                        if let NgramProbability::Found(_) = probability {
+                            *cnt += 1;
+                        }
+                    }
+
+                    Some(NgramExplanation {
+                        ngram,
+                        probabilities,
+                        cumulative: totals.map(|(sum, _)| sum),
+                    })
+                })
+                .collect();
+
+            explanation
+                .ngram_sizes
+                .push(NgramSizeExplanation { ngram_size, ngrams });
+        }
+        explanation.totals = totals;
+
+        explanation
+    }
+}
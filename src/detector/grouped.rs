@@ -0,0 +1,58 @@
+use super::Detector;
+use alphabet_detector::ScriptLanguage;
+
+impl Detector<'_> {
+    /// Returns relative probabilities (see [`probabilities_relative`](Self::probabilities_relative))
+    /// summed by groups, which languages are mapped to by `group`
+    /// (e.g. Norwegian for Bokmal and Nynorsk), ordered by probability.
+    ///
+    /// Languages mapped to equal groups don't split the probability,
+    /// so close languages can't lose to a third one.
+    pub fn probabilities_grouped<G: PartialEq>(
+        &self,
+        text: &str,
+        group: impl Fn(ScriptLanguage) -> G,
+    ) -> Vec<(G, f64)> {
+        let mut res: Vec<(G, f64)> = Vec::new();
+        for (language, probability) in self.probabilities_relative(text) {
+            let g = group(language);
+            match res.iter_mut().find(|(r, _)| *r == g) {
+                Some((_, p)) => *p += probability,
+                None => res.push((g, probability)),
+            }
+        }
+        // stable, so equal groups are ordered by their top language
+        res.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        res
+    }
+
+    /// Detects a top one group of the provided text, see [`probabilities_grouped`](Self::probabilities_grouped).
+    ///
+    /// `minimum_distance` is a distance between a first and a second relative probabilities
+    /// (a number between 0.0 and 1.0).
+    ///
+    /// If a single group cannot be returned, [`None`] is returned.
+    pub fn detect_top_one_grouped<G: PartialEq>(
+        &self,
+        text: &str,
+        group: impl Fn(ScriptLanguage) -> G,
+        minimum_distance: f64,
+    ) -> Option<G> {
+        debug_assert!(minimum_distance >= 0.0, "Minimum distance must be >= 0.0");
+
+        let mut probabilities = self.probabilities_grouped(text, group).into_iter();
+
+        let (first_group, first_probability) = probabilities.next()?;
+        let Some((_, second_probability)) = probabilities.next() else {
+            return Some(first_group);
+        };
+
+        let probabilities_diff = first_probability - second_probability;
+        if probabilities_diff < f64::EPSILON || probabilities_diff < minimum_distance {
+            return None;
+        }
+
+        Some(first_group)
+    }
+}
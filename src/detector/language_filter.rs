@@ -0,0 +1,133 @@
+use alphabet_detector::{Language, Script, ScriptLanguage, UcdScript};
+
+/// Official languages of the European Union
+const EU_OFFICIAL: [ScriptLanguage; 24] = [
+    ScriptLanguage::Bulgarian,
+    ScriptLanguage::Croatian,
+    ScriptLanguage::Czech,
+    ScriptLanguage::Danish,
+    ScriptLanguage::Dutch,
+    ScriptLanguage::English,
+    ScriptLanguage::Estonian,
+    ScriptLanguage::Finnish,
+    ScriptLanguage::French,
+    ScriptLanguage::German,
+    ScriptLanguage::Greek,
+    ScriptLanguage::Hungarian,
+    ScriptLanguage::Irish,
+    ScriptLanguage::Italian,
+    ScriptLanguage::Latvian,
+    ScriptLanguage::Lithuanian,
+    ScriptLanguage::Maltese,
+    ScriptLanguage::Polish,
+    ScriptLanguage::Portuguese,
+    ScriptLanguage::Romanian,
+    ScriptLanguage::Slovak,
+    ScriptLanguage::Slovene,
+    ScriptLanguage::Spanish,
+    ScriptLanguage::Swedish,
+];
+
+/// Named group of languages, for [`LanguageFilter`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LanguageGroup {
+    /// Official languages of the European Union
+    EuOfficial,
+    /// Languages written in Han, Kana, Hangul or Bopomofo
+    Cjk,
+    /// Languages written in the Brahmic scripts of the Indian subcontinent
+    Indic,
+}
+
+impl LanguageGroup {
+    pub fn contains(self, language: ScriptLanguage) -> bool {
+        match self {
+            Self::EuOfficial => EU_OFFICIAL.contains(&language),
+            Self::Cjk => matches!(
+                UcdScript::from(language),
+                UcdScript::Han
+                    | UcdScript::Hiragana
+                    | UcdScript::Katakana
+                    | UcdScript::Hangul
+                    | UcdScript::Bopomofo
+            ),
+            Self::Indic => matches!(
+                UcdScript::from(language),
+                UcdScript::Devanagari
+                    | UcdScript::Bengali
+                    | UcdScript::Gurmukhi
+                    | UcdScript::Gujarati
+                    | UcdScript::Oriya
+                    | UcdScript::Tamil
+                    | UcdScript::Telugu
+                    | UcdScript::Kannada
+                    | UcdScript::Malayalam
+                    | UcdScript::Sinhala
+            ),
+        }
+    }
+}
+
+/// Selects languages for [`DetectorBuilder::include`](super::DetectorBuilder::include)
+/// and [`DetectorBuilder::exclude`](super::DetectorBuilder::exclude).
+///
+/// Matches are checked against all `alphabet_detector` languages on build,
+/// so new languages are selected without changing the filters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LanguageFilter {
+    ScriptLanguage(ScriptLanguage),
+    /// Language in any script
+    Language(Language),
+    /// All languages of the `ISO 15924` script
+    Script(Script),
+    /// All languages of the Unicode script
+    UcdScript(UcdScript),
+    Group(LanguageGroup),
+}
+
+impl LanguageFilter {
+    pub fn matches(self, language: ScriptLanguage) -> bool {
+        match self {
+            Self::ScriptLanguage(l) => l == language,
+            Self::Language(l) => Language::from(language) == l,
+            Self::Script(s) => Script::from(language) == s,
+            Self::UcdScript(s) => UcdScript::from(language) == s,
+            Self::Group(g) => g.contains(language),
+        }
+    }
+}
+
+impl From<ScriptLanguage> for LanguageFilter {
+    #[inline]
+    fn from(v: ScriptLanguage) -> Self {
+        Self::ScriptLanguage(v)
+    }
+}
+
+impl From<Language> for LanguageFilter {
+    #[inline]
+    fn from(v: Language) -> Self {
+        Self::Language(v)
+    }
+}
+
+impl From<Script> for LanguageFilter {
+    #[inline]
+    fn from(v: Script) -> Self {
+        Self::Script(v)
+    }
+}
+
+impl From<UcdScript> for LanguageFilter {
+    #[inline]
+    fn from(v: UcdScript) -> Self {
+        Self::UcdScript(v)
+    }
+}
+
+impl From<LanguageGroup> for LanguageFilter {
+    #[inline]
+    fn from(v: LanguageGroup) -> Self {
+        Self::Group(v)
+    }
+}
@@ -1,5 +1,11 @@
-use super::{builder::DetectorBuilder, *};
-use crate::{model::Model, NgramSize, ScriptLanguage::*};
+use super::{builder::DetectorBuilder, Context, *};
+use crate::{
+    bin_storage::ProbabilityEncoding,
+    calibration::{Calibration, CalibrationBucket},
+    model::Model,
+    NgramSize,
+    ScriptLanguage::*,
+};
 use ::std::sync::LazyLock;
 use ahash::AHashMap;
 use float_cmp::approx_eq;
@@ -86,7 +92,8 @@ fn model_for_german() -> Model {
 
 static MOCK_MODELS_ENGLISH_AND_GERMAN: LazyLock<ModelsStorage> = LazyLock::new(|| {
     let models_storage =
-        ModelsStorage::from_models([(English, model_for_english()), (German, model_for_german())]);
+        ModelsStorage::from_models([(English, model_for_english()), (German, model_for_german())])
+            .unwrap();
     assert!(models_storage.wordgram_min_probability < 0.0);
     assert!(models_storage.wordgram_min_probability > f64::NEG_INFINITY);
     models_storage
@@ -253,6 +260,382 @@ fn test_mock_detect_top_one_raw(word: &str, expected_language: Option<ScriptLang
     assert_eq!(detected_language, expected_language);
 }
 
+#[test]
+fn test_mock_detect() {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .minlen(6)
+        .build();
+
+    let result = detector.detect("Alter");
+    assert_eq!(result.probabilities.first().map(|(l, _)| *l), Some(German));
+    assert_eq!(result.words.len(), 1);
+    assert_eq!(result.characters_count, 5);
+    assert_eq!(result.ngram_sizes, detector.short_text_ngram_sizes);
+    assert!(!result.long_text);
+    assert!(!result.single_language);
+
+    let result = detector.detect("Alter alter");
+    assert_eq!(result.characters_count, 10);
+    assert_eq!(result.ngram_sizes, detector.long_text_ngram_sizes);
+    assert!(result.long_text);
+
+    let detector = detector.clone_with_languages([German].into_iter().collect());
+    let result = detector.detect("Alter");
+    assert_eq!(result.probabilities, [(German, 0.0)]);
+    assert!(result.ngram_sizes.is_empty());
+    assert!(result.single_language);
+}
+
+#[rstest(
+    text,
+    max_deviation,
+    expected,
+    case("Alter", 4.0, DetectedLanguage::Known(German)),
+    case("Alter alter", 4.0, DetectedLanguage::Unknown),
+    case("Alter alter", 6.0, DetectedLanguage::Known(German)),
+    case("Alter", 0.0, DetectedLanguage::Unknown),
+    case("rot", 2.0, DetectedLanguage::Known(German)),
+    case("rot tor ort rot tor", 2.0, DetectedLanguage::Unknown),
+    case("kala", 4.0, DetectedLanguage::Known(German)),
+    case("kala kola kalo kola kala", 4.0, DetectedLanguage::Unknown),
+    case("qqqqq", 2.0, DetectedLanguage::Unknown),
+    case("", 2.0, DetectedLanguage::Unknown)
+)]
+fn test_mock_detect_or_unknown(text: &str, max_deviation: f64, expected: DetectedLanguage) {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+
+    assert_eq!(detector.detect_or_unknown(text, max_deviation), expected);
+}
+
+#[test]
+fn test_mock_detect_early_exit() {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+    let text = ["Alter ok"; 20].join(" ");
+
+    let result = detector.detect(&text);
+    assert_eq!(result.words.len(), 40);
+    assert_eq!(result.consumed_words, 40);
+
+    // never stops, ngrams are deduplicated across blocks
+    let result_no_exit = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .early_exit(f64::INFINITY)
+        .build()
+        .detect(&text);
+    assert_eq!(result_no_exit.consumed_words, 40);
+    assert_probabilities_approx_eq(result_no_exit.probabilities, result.probabilities);
+
+    let result_exit = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .early_exit(0.0)
+        .build()
+        .detect(&text);
+    assert_eq!(result_exit.consumed_words, 16);
+    assert_eq!(result_exit.words.len(), 40);
+    assert_eq!(
+        result_exit.probabilities.first().map(|(l, _)| *l),
+        Some(German)
+    );
+}
+
+#[test]
+fn test_mock_probabilities_with_scratch_early_exit() {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .early_exit(0.0)
+        .build();
+    // exits after the first block of words
+    let text = [["ok"; 16].join(" "), ["Alter"; 16].join(" ")].join(" ");
+    assert_eq!(detector.detect(&text).consumed_words, 16);
+    assert_eq!(detector.detect_top_one_raw(&text), Some(English));
+    let detector_no_exit = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+    assert_eq!(detector_no_exit.detect_top_one_raw(&text), Some(German));
+
+    let mut scratch = DetectorScratch::new();
+    for text in [text.as_str(), "Alter ok", "ok"] {
+        assert_probabilities_approx_eq(
+            detector
+                .probabilities_with_scratch(text, &mut scratch)
+                .to_vec(),
+            detector.probabilities(text),
+        );
+    }
+    assert_eq!(
+        detector.detect_batch(&[&text, "Alter"]),
+        [Some(English), Some(German)]
+    );
+}
+
+#[test]
+fn test_mock_probabilities_with_scratch() {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+    let texts = ["Alter", "", "Alter ok", "ok", "Älter alter"];
+
+    let mut scratch = DetectorScratch::new();
+    for text in texts {
+        assert_probabilities_approx_eq(
+            detector
+                .probabilities_with_scratch(text, &mut scratch)
+                .to_vec(),
+            detector.probabilities(text),
+        );
+    }
+
+    let expected: Vec<_> = texts
+        .iter()
+        .map(|text| detector.detect_top_one_raw(text))
+        .collect();
+    assert_eq!(detector.detect_batch(&texts), expected);
+}
+
+#[cfg(feature = "rayon")]
+#[rstest(
+    early_exit,
+    priors,
+    case(None, vec![]),
+    case(Some(0.0), vec![]),
+    case(None, vec![(English, 2.0)])
+)]
+fn test_mock_par_detect(early_exit: Option<f64>, priors: Vec<(ScriptLanguage, f64)>) {
+    let mut builder = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .priors(priors);
+    if let Some(distance) = early_exit {
+        builder = builder.early_exit(distance);
+    }
+    let detector = builder.build();
+    let long_text = [["ok"; 16].join(" "), ["Alter"; 16].join(" ")].join(" ");
+    let texts: Vec<String> = ["Alter", "", "Alter ok", "ok", "Älter alter", &long_text]
+        .iter()
+        .cycle()
+        .take(100)
+        .map(|text| text.to_string())
+        .collect();
+
+    let probabilities = detector.par_probabilities(&texts);
+    assert_eq!(probabilities.len(), texts.len());
+    for (text, probabilities) in texts.iter().zip(probabilities) {
+        assert_probabilities_approx_eq(probabilities, detector.probabilities(text));
+    }
+
+    let expected: Vec<_> = texts
+        .iter()
+        .map(|text| detector.detect_top_one_raw(text))
+        .collect();
+    assert_eq!(detector.par_detect_top_one(&texts), expected);
+}
+
+#[rstest(
+    text,
+    priors,
+    expected_language,
+    case("Alter", vec![], German),
+    case("Alter", vec![(English, 1.0)], German),
+    case("Alter", vec![(English, 10.0)], English),
+    case("Alter", vec![(German, -10.0), (English, 0.0)], English),
+    case("Alter", vec![(English, 5.0)], German)
+)]
+fn test_mock_priors(
+    text: &str,
+    priors: Vec<(ScriptLanguage, f64)>,
+    expected_language: ScriptLanguage,
+) {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .priors(priors)
+        .build();
+
+    let probabilities = detector.probabilities(text);
+    assert_eq!(
+        probabilities.first().map(|(l, _)| *l),
+        Some(expected_language)
+    );
+    assert_probabilities_approx_eq(
+        detector
+            .probabilities_with_scratch(text, &mut DetectorScratch::new())
+            .to_vec(),
+        probabilities,
+    );
+}
+
+#[test]
+fn test_mock_probabilities_with_context() {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+    let english_probability = |probabilities: Vec<(ScriptLanguage, f64)>| {
+        probabilities
+            .into_iter()
+            .find(|(l, _)| *l == English)
+            .unwrap()
+            .1
+    };
+
+    let probabilities = detector.probabilities("Alter");
+    assert_eq!(probabilities.first().map(|(l, _)| *l), Some(German));
+    assert_eq!(
+        detector.probabilities_with_context("Alter", &Context::default()),
+        probabilities
+    );
+
+    let context = Context::with_previous_languages([English, English, English, German]);
+    assert_eq!(context.prior(English), 4f64.ln());
+    assert_eq!(context.prior(German), 2f64.ln());
+    assert_eq!(context.prior(French), 0.0);
+    assert!(
+        english_probability(detector.probabilities_with_context("Alter", &context))
+            > english_probability(probabilities)
+    );
+
+    let context = Context::with_priors([(English, 10.0)]);
+    assert_eq!(
+        detector
+            .probabilities_with_context("Alter", &context)
+            .first()
+            .map(|(l, _)| *l),
+        Some(English)
+    );
+}
+
+fn assert_probabilities_approx_eq(
+    probabilities: Vec<(ScriptLanguage, f64)>,
+    expected: Vec<(ScriptLanguage, f64)>,
+) {
+    assert_eq!(probabilities.len(), expected.len());
+    for ((language, probability), (expected_language, expected_probability)) in
+        probabilities.into_iter().zip(expected)
+    {
+        assert_eq!(language, expected_language);
+        assert!(approx_eq!(f64, probability, expected_probability, ulps = 2));
+    }
+}
+
+// ngrams are not repeated in different chunks, so probabilities are equal
+#[rstest(
+    chunks,
+    case(&["Alter"]),
+    case(&["Al", "ter"]),
+    case(&["Alter o", "k"]),
+    case(&["", "Alter", " ", "ok "])
+)]
+fn test_mock_stream(chunks: &[&str]) {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+
+    let mut stream = detector.stream();
+    for chunk in chunks {
+        stream.push(chunk);
+    }
+    assert_probabilities_approx_eq(stream.finish(), detector.probabilities(&chunks.concat()));
+}
+
+#[test]
+fn test_mock_stream_read_from() {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+
+    let text = "Älter ok";
+    // splits the first char
+    let reader = ::std::io::Read::chain(&text.as_bytes()[..1], &text.as_bytes()[1..]);
+    assert_probabilities_approx_eq(
+        detector.probabilities_reader(reader).unwrap(),
+        detector.probabilities(text),
+    );
+
+    // stops after the first read
+    let mut stream = detector.stream();
+    let reader = ::std::io::Read::chain(&b"Alter "[..], &b"ok"[..]);
+    stream.read_from(reader, Some(0.0)).unwrap();
+    assert_eq!(stream.characters_count(), 5);
+    assert_eq!(stream.detect_top_one_or_none(0.0), Some(German));
+
+    let err = detector
+        .probabilities_reader(&b"Alter \xff"[..])
+        .unwrap_err();
+    assert_eq!(err.kind(), ::std::io::ErrorKind::InvalidData);
+}
+
+#[rstest(
+    text,
+    priors,
+    case("Alter", vec![]),
+    case("Alter alter", vec![]),
+    case("altet", vec![]),
+    case("Alter", vec![(English, 2.0), (German, -1.0)])
+)]
+fn test_mock_explain(text: &str, priors: Vec<(ScriptLanguage, f64)>) {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .priors(priors)
+        .build();
+
+    let explanation = detector.explain(text, English, German);
+    let probabilities = detector.probabilities(text);
+    for (language, probability) in explanation
+        .languages
+        .iter()
+        .zip(explanation.probabilities())
+    {
+        let expected = probabilities.iter().find(|(l, _)| l == language).unwrap().1;
+        assert!(approx_eq!(f64, probability, expected, ulps = 2));
+    }
+    assert!(!explanation.ngram_sizes.is_empty());
+    assert!(explanation.to_string().contains("Total: English"));
+}
+
+#[rstest(
+    text,
+    expected_segments,
+    case("", vec![]),
+    case("Alter alter", vec![(0..11, German)]),
+    // "kor" is more probable in English, but not enough to pay two language switches
+    case("Alter kor Alter", vec![(0..15, German)]),
+    case(
+        "Alter alte lt lt lt ok ok",
+        vec![(0..19, German), (20..25, English)]
+    )
+)]
+fn test_mock_detect_segments(
+    text: &str,
+    expected_segments: Vec<(::core::ops::Range<usize>, ScriptLanguage)>,
+) {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+    let segments = detector.detect_segments(text);
+    assert!(segments.iter().all(|s| (0.0..=1.0).contains(&s.confidence)));
+    let segments: Vec<_> = segments
+        .into_iter()
+        .map(|s| (s.range, s.language))
+        .collect();
+    assert_eq!(segments, expected_segments);
+}
+
+#[rstest(
+    text,
+    expected_shares,
+    case("", vec![]),
+    case("Alter alter", vec![(German, 1.0)])
+)]
+fn test_mock_language_shares(text: &str, expected_shares: Vec<(ScriptLanguage, f64)>) {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+    let shares = detector.language_shares(text);
+    assert_eq!(shares, expected_shares);
+}
+
 #[rstest(
     word,
     expected_language,
@@ -273,3 +656,205 @@ fn test_mock_detect_top_one_or_none_no_filter(
     let detected_language = detector.detect_top_one_or_none(word, 0.0);
     assert_eq!(detected_language, expected_language);
 }
+
+#[test]
+fn test_mock_probabilities_calibrated() {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+    let relative = detector.probabilities_relative("Alter");
+    assert_eq!(detector.probabilities_calibrated("Alter"), relative);
+
+    let mut bin_storage = MOCK_MODELS_ENGLISH_AND_GERMAN.to_bin_storage().unwrap();
+    bin_storage.set_calibration(Calibration::new([CalibrationBucket {
+        min_characters: 0,
+        temperature: 2.0,
+    }]));
+    let models_storage = ModelsStorage::from_vec(bin_storage.to_bytes().unwrap()).unwrap();
+    assert_eq!(models_storage.calibration(), *bin_storage.calibration());
+
+    let detector = DetectorBuilder::new(&models_storage)
+        .languages(ahashset!(English, German))
+        .build();
+    let calibrated = detector.probabilities_calibrated("Alter");
+    assert_eq!(calibrated[0].0, German);
+    assert!(calibrated[0].1 > relative[0].1);
+
+    // scaled logarithmic probabilities are far below `exp` underflow
+    let temperature = 1000.0;
+    bin_storage.set_calibration(Calibration::new([CalibrationBucket {
+        min_characters: 0,
+        temperature,
+    }]));
+    let models_storage = ModelsStorage::from_vec(bin_storage.to_bytes().unwrap()).unwrap();
+    let detector = DetectorBuilder::new(&models_storage)
+        .languages(ahashset!(English, German))
+        .build();
+    let probabilities = detector.probabilities("Alter");
+    assert!(probabilities.iter().all(|(_, p)| p * temperature < -745.0));
+    let calibrated = detector.probabilities_calibrated("Alter");
+    assert_eq!(calibrated.len(), 2);
+    let max = probabilities[0].1 * temperature;
+    let denominator: f64 = probabilities
+        .iter()
+        .map(|(_, p)| (p * temperature - max).exp())
+        .sum();
+    for ((language, probability), (expected_language, p)) in calibrated.iter().zip(&probabilities) {
+        assert_eq!(language, expected_language);
+        let expected = (p * temperature - max).exp() / denominator;
+        assert!(approx_eq!(f64, *probability, expected, ulps = 2));
+    }
+}
+
+#[test]
+fn test_mock_merge() {
+    let overlay = ModelsStorage::from_models([(
+        German,
+        create_mock_model([
+            ahashmap!("x" => 1.0),
+            ahashmap!(),
+            ahashmap!(),
+            ahashmap!(),
+            ahashmap!(),
+            ahashmap!(),
+        ]),
+    )])
+    .unwrap();
+    let merged = ModelsStorage::merge([&*MOCK_MODELS_ENGLISH_AND_GERMAN, &overlay]).unwrap();
+
+    let languages = |ngram: &str| -> Vec<ScriptLanguage> {
+        merged.ngrams[ngram.chars().count() - 1]
+            .get(ngram)
+            .map(|v| {
+                v.iter()
+                    .map(|ArchivedTuple2(l, _)| unsafe {
+                        ScriptLanguage::transmute_from_usize(l.to_native() as usize)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    assert_eq!(languages("alt"), [English]);
+    assert_eq!(languages("o"), [English]);
+    assert_eq!(languages("x"), [German]);
+}
+
+#[rstest(
+    encoding,
+    max_error,
+    case(ProbabilityEncoding::U16, 0.0001),
+    case(ProbabilityEncoding::U8, 0.02)
+)]
+fn test_mock_quantized_probabilities(encoding: ProbabilityEncoding, max_error: f64) {
+    let bytes = MOCK_MODELS_ENGLISH_AND_GERMAN
+        .to_bin_storage()
+        .unwrap()
+        .to_bytes_encoded(encoding)
+        .unwrap();
+    let quantized_storage = ModelsStorage::from_vec(bytes).unwrap();
+
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+    let quantized_detector = DetectorBuilder::new(&quantized_storage)
+        .languages(ahashset!(English, German))
+        .build();
+
+    for text in ["Alter", "alter", "groß", "k", "o"] {
+        let probabilities = detector.probabilities(text);
+        let quantized_probabilities = quantized_detector.probabilities(text);
+        assert_eq!(probabilities.len(), quantized_probabilities.len());
+
+        for ((language, probability), (quantized_language, quantized_probability)) in
+            probabilities.into_iter().zip(quantized_probabilities)
+        {
+            assert_eq!(language, quantized_language);
+            assert!(
+                probability == quantized_probability
+                    || (probability - quantized_probability).abs() <= max_error,
+                "expected probability {probability} for language '{language:?}', got {quantized_probability}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_mock_metadata() {
+    let metadata = MOCK_MODELS_ENGLISH_AND_GERMAN.metadata();
+    assert_eq!(metadata.format_version, crate::bin_storage::FORMAT_VERSION);
+    assert_eq!(metadata.crate_version, env!("CARGO_PKG_VERSION"));
+    assert!(metadata.created_at > 0);
+    assert_eq!(metadata.languages, [English, German]);
+    assert_eq!(
+        metadata.ngram_sizes,
+        [
+            NgramSize::Uni,
+            NgramSize::Bi,
+            NgramSize::Tri,
+            NgramSize::Quadri,
+            NgramSize::Five,
+            NgramSize::Word,
+        ]
+    );
+    assert_eq!(metadata.encoding, ProbabilityEncoding::F64);
+}
+
+#[test]
+fn test_mock_include_exclude() {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .include([LanguageGroup::EuOfficial])
+        .exclude([English])
+        .build();
+    assert_eq!(detector.languages.len(), 23);
+    assert!(detector.languages.contains(&German));
+    assert!(!detector.languages.contains(&English));
+    assert!(!detector.languages.contains(&Russian));
+
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German, Russian))
+        .include([German])
+        .include([LanguageFilter::ScriptLanguage(Russian)])
+        .build();
+    assert_eq!(detector.languages, FxHashSet::from_iter([German, Russian]));
+
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .include::<LanguageFilter>([])
+        .build();
+    assert!(detector.languages.is_empty());
+
+    assert_eq!(detector.detect_top_one_raw("Alter"), None);
+}
+
+#[test]
+fn test_mock_probabilities_grouped() {
+    let detector = DetectorBuilder::new(&MOCK_MODELS_ENGLISH_AND_GERMAN)
+        .languages(ahashset!(English, German))
+        .build();
+
+    let mut probabilities = detector.probabilities_grouped("Alter", |l| l);
+    probabilities
+        .iter_mut()
+        .for_each(|(_, p)| *p = round_to_two_decimal_places(*p));
+    assert_eq!(probabilities, vec![(German, 0.61), (English, 0.39)]);
+
+    let probabilities = detector.probabilities_grouped("Alter", |_| "Germanic");
+    assert_eq!(probabilities.len(), 1);
+    assert_eq!(probabilities[0].0, "Germanic");
+    assert!(approx_eq!(f64, probabilities[0].1, 1.0, ulps = 2));
+
+    assert_eq!(
+        detector.detect_top_one_grouped("Alter", |l| l, 0.0),
+        Some(German)
+    );
+    assert_eq!(detector.detect_top_one_grouped("Alter", |l| l, 0.5), None);
+    assert_eq!(
+        detector.detect_top_one_grouped("Alter", |_| "Germanic", 0.5),
+        Some("Germanic")
+    );
+    assert_eq!(detector.detect_top_one_grouped("o", |l| l, 0.0), None);
+    assert_eq!(
+        detector.detect_top_one_grouped("проарплап", |l| l, 0.0),
+        None
+    );
+}
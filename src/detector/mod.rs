@@ -1,7 +1,7 @@
 use crate::{
-    detector::storage::NgramModel,
+    detector::storage::{NgramModel, NgramModelFn, Probability},
     ngram_size::{NgramSize, NgramSizes, NgramSizesTrait},
-    ngrams::ngram_iterator,
+    ngrams::{ngram_iterator, ngram_iterator_with_seen},
 };
 use ::core::cmp::Ordering;
 use ::std::borrow::Borrow;
@@ -9,16 +9,97 @@ use alphabet_detector::{
     fulltext_filter_with_margin, slang_arr_default, ScriptLanguage, ScriptLanguageArr, Word,
 };
 use debug_unsafe::{option::OptionUnwrapper, slice::SliceGetter};
+use strum::EnumCount;
 
 mod builder;
+mod context;
+mod explain;
+mod grouped;
+mod language_filter;
 #[cfg(all(debug_assertions, test))]
 mod mock_tests;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod scratch;
+mod segments;
 mod storage;
+mod stream;
+mod unknown;
 
 pub use builder::DetectorBuilder;
-use rkyv::tuple::ArchivedTuple2;
-use rustc_hash::FxHashSet;
-pub use storage::{ModelsStorage, ModelsStorageError};
+pub use context::Context;
+pub use explain::{Explanation, NgramExplanation, NgramProbability, NgramSizeExplanation};
+pub use language_filter::{LanguageFilter, LanguageGroup};
+use rkyv::{tuple::ArchivedTuple2, Archived};
+use rustc_hash::{FxHashMap, FxHashSet};
+pub use scratch::DetectorScratch;
+pub use segments::Segment;
+pub use storage::{ModelsStorage, ModelsStorageError, ModelsStorageOptions};
+pub use stream::DetectorStream;
+pub use unknown::DetectedLanguage;
+
+/// Adds logarithmic probabilities of ngrams to `output` for each of `languages`.
+/// Languages without the ngram get their min probability, but it's not counted
+/// (ngrams not found in any language are skipped).
+struct NgramsSumCnt<'a, I, F> {
+    ngrams_iter: I,
+    languages: &'a FxHashSet<ScriptLanguage>,
+    output: &'a mut ScriptLanguageArr<(f64, usize)>,
+    min_prob_getter: F,
+}
+
+impl<I, F> NgramModelFn for NgramsSumCnt<'_, I, F>
+where
+    I: Iterator,
+    I::Item: Borrow<str>,
+    F: Fn(ScriptLanguage) -> f64,
+{
+    type Output = ();
+
+    #[inline]
+    fn call<P: Probability>(self, ngram_model: &NgramModel<P>, table: &[Archived<f64>]) {
+        let Self {
+            ngrams_iter,
+            languages,
+            output,
+            min_prob_getter,
+        } = self;
+        for ngram in ngrams_iter {
+            let Some(langs_probs) = ngram_model.get(ngram.borrow()).filter(|v| !v.is_empty())
+            else {
+                continue;
+            };
+
+            let mut found = LanguagesBitSet::default();
+            for ArchivedTuple2(language, prob) in langs_probs.iter() {
+                let language =
+                    unsafe { ScriptLanguage::transmute_from_usize(language.to_native() as usize) };
+
+                if !languages.contains(&language) || !found.insert(language) {
+                    continue;
+                }
+                let prob = P::dequantize(prob, table);
+
+                output
+                    .get_safe_unchecked_mut(language as usize)
+                    .add((prob, 1));
+            }
+
+            if found.is_empty() {
+                continue;
+            }
+            languages
+                .iter()
+                .filter(|&&language| !found.contains(language))
+                .for_each(|&language| {
+                    output.get_safe_unchecked_mut(language as usize).0 += min_prob_getter(language);
+                });
+        }
+    }
+}
+
+/// Words scored between checks of [`DetectorBuilder::early_exit`]
+const EARLY_EXIT_WORDS: usize = 16;
 
 trait ProbabilitiesAdder: Sized {
     fn add(&mut self, add: (f64, usize));
@@ -32,6 +113,33 @@ impl ProbabilitiesAdder for (f64, usize) {
     }
 }
 
+/// Set of languages, which doesn't allocate
+#[derive(Default)]
+struct LanguagesBitSet([u64; ScriptLanguage::COUNT.div_ceil(64)]);
+
+impl LanguagesBitSet {
+    /// Returns `true` if the language was not present
+    #[inline(always)]
+    fn insert(&mut self, language: ScriptLanguage) -> bool {
+        let (i, bit) = (language as usize / 64, 1 << (language as usize % 64));
+        let block = self.0.get_safe_unchecked_mut(i);
+        let absent = *block & bit == 0;
+        *block |= bit;
+        absent
+    }
+
+    #[inline(always)]
+    fn contains(&self, language: ScriptLanguage) -> bool {
+        let (i, bit) = (language as usize / 64, 1 << (language as usize % 64));
+        self.0.get_safe_unchecked(i) & bit != 0
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|&block| block == 0)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Detector<'m> {
     models_storage: &'m ModelsStorage<'m>,
@@ -39,6 +147,10 @@ pub struct Detector<'m> {
     pub long_text_minlen: usize,
     long_text_ngram_sizes: NgramSizes,
     short_text_ngram_sizes: NgramSizes,
+    /// See [`DetectorBuilder::early_exit`]
+    pub early_exit_distance: Option<f64>,
+    /// See [`DetectorBuilder::priors`]
+    pub priors: FxHashMap<ScriptLanguage, f64>,
 }
 
 impl<'m> Detector<'m> {
@@ -80,10 +192,22 @@ impl<'m> Detector<'m> {
 
         Self {
             models_storage: builder.models_storage,
-            languages: builder.languages.into_iter().collect(),
+            languages: builder
+                .languages
+                .into_iter()
+                .filter(|&language| {
+                    builder
+                        .included
+                        .as_ref()
+                        .is_none_or(|included| included.iter().any(|f| f.matches(language)))
+                        && !builder.excluded.iter().any(|f| f.matches(language))
+                })
+                .collect(),
             long_text_minlen: builder.long_text_minlen,
             long_text_ngram_sizes,
             short_text_ngram_sizes,
+            early_exit_distance: builder.early_exit_distance,
+            priors: builder.priors,
         }
     }
 
@@ -96,44 +220,8 @@ impl<'m> Detector<'m> {
             long_text_minlen: self.long_text_minlen,
             long_text_ngram_sizes: self.long_text_ngram_sizes.clone(),
             short_text_ngram_sizes: self.short_text_ngram_sizes.clone(),
-        }
-    }
-
-    #[inline]
-    fn ngrams_sum_cnt(
-        ngram_model: &'m NgramModel,
-        ngrams_iter: impl Iterator<Item = impl Borrow<str>>,
-        languages: &FxHashSet<ScriptLanguage>,
-        output: &mut ScriptLanguageArr<(f64, usize)>,
-        min_prob_getter: impl Fn(ScriptLanguage) -> f64,
-    ) {
-        for ngram in ngrams_iter {
-            let Some(langs_probs) = ngram_model.get(ngram.borrow()).filter(|v| !v.is_empty())
-            else {
-                continue;
-            };
-
-            let mut languages_tmp = languages.clone();
-            for ArchivedTuple2(language, prob) in langs_probs.iter() {
-                let language =
-                    unsafe { ScriptLanguage::transmute_from_usize(language.to_native() as usize) };
-
-                if !languages_tmp.remove(&language) {
-                    continue;
-                }
-                let prob = prob.to_native();
-
-                output
-                    .get_safe_unchecked_mut(language as usize)
-                    .add((prob, 1));
-            }
-
-            if languages_tmp.len() == languages.len() {
-                continue;
-            }
-            languages_tmp.into_iter().for_each(|language| {
-                output.get_safe_unchecked_mut(language as usize).0 += min_prob_getter(language);
-            });
+            early_exit_distance: self.early_exit_distance,
+            priors: self.priors.clone(),
         }
     }
 
@@ -144,19 +232,20 @@ impl<'m> Detector<'m> {
         languages: &FxHashSet<ScriptLanguage>,
         output: &mut ScriptLanguageArr<(f64, usize)>,
     ) {
-        Self::ngrams_sum_cnt(
+        let min_prob_getter = |language: ScriptLanguage| {
             models_storage
-                .ngrams
-                .get_safe_unchecked(ngram_size as usize),
-            ngrams_iter,
-            languages,
-            output,
-            #[inline]
-            |language| {
-                models_storage
-                    .langs_ngram_min_probability
-                    .get_safe_unchecked(language as usize)
-                    .to_native()
+                .langs_ngram_min_probability
+                .get_safe_unchecked(language as usize)
+                .to_native()
+        };
+
+        models_storage.with_ngram_model(
+            ngram_size,
+            NgramsSumCnt {
+                ngrams_iter,
+                languages,
+                output,
+                min_prob_getter,
             },
         );
     }
@@ -167,13 +256,16 @@ impl<'m> Detector<'m> {
         languages: &FxHashSet<ScriptLanguage>,
         output: &mut ScriptLanguageArr<(f64, usize)>,
     ) {
-        Self::ngrams_sum_cnt(
-            models_storage.wordgrams,
-            ngrams_iter,
-            languages,
-            output,
-            #[inline]
-            |_| models_storage.wordgram_min_probability,
+        let min_prob_getter = |_| models_storage.wordgram_min_probability;
+
+        models_storage.with_ngram_model(
+            NgramSize::Word,
+            NgramsSumCnt {
+                ngrams_iter,
+                languages,
+                output,
+                min_prob_getter,
+            },
         );
     }
 
@@ -197,37 +289,169 @@ impl<'m> Detector<'m> {
         );
     }
 
+    /// Mean of the sum of logarithmic probabilities of `cnt` ngrams,
+    /// with the language logarithmic priors (of the detector and `context`) added to the sum
+    #[inline]
+    fn language_mean(
+        &self,
+        language: ScriptLanguage,
+        (p, cnt): (f64, usize),
+        context: Option<&Context>,
+    ) -> f64 {
+        if cnt == 0 {
+            return f64::NEG_INFINITY;
+        }
+
+        let mut prior = self.priors.get(&language).copied().unwrap_or_default();
+        if let Some(context) = context {
+            prior += context.prior(language);
+        }
+        (p + prior) / cnt as f64
+    }
+
     /// Computes mean average for each language
     #[inline]
     fn probabilities_mean(
+        &self,
         probabilities: ScriptLanguageArr<(f64, usize)>,
         filtered_languages: FxHashSet<ScriptLanguage>,
+        context: Option<&Context>,
     ) -> Vec<(ScriptLanguage, f64)> {
         let mut res = Vec::with_capacity(filtered_languages.len());
         for language in filtered_languages.into_iter() {
-            let (p, cnt) = *probabilities.get_safe_unchecked(language as usize);
             res.push((
                 language,
-                if cnt == 0 {
-                    f64::NEG_INFINITY
-                } else {
-                    p / cnt as f64
-                },
+                self.language_mean(
+                    language,
+                    *probabilities.get_safe_unchecked(language as usize),
+                    context,
+                ),
             ));
         }
 
         res
     }
 
-    /// Returns probabilities for the provided text.
-    /// Each value of `probabilities` in `ProbabilitiesExtra` is a logarithmic probability
-    /// between a negative infinity and 0.0. Also contains words.
+    /// Difference between the top two mean logarithmic probabilities.
+    /// Languages without found ngrams are not counted.
+    fn leader_distance(
+        &self,
+        probabilities: &ScriptLanguageArr<(f64, usize)>,
+        languages: &FxHashSet<ScriptLanguage>,
+        context: Option<&Context>,
+    ) -> f64 {
+        let (mut first, mut second) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for &language in languages {
+            let p = self.language_mean(
+                language,
+                *probabilities.get_safe_unchecked(language as usize),
+                context,
+            );
+            if p > first {
+                second = first;
+                first = p;
+            } else if p > second {
+                second = p;
+            }
+        }
+
+        first - second
+    }
+
+    /// Returns sorted logarithmic probabilities of `words`, and the count of scored words.
     ///
-    /// Result is sorted by probabilities in a descending order.
+    /// With `early_exit_distance` set, `words` are scored in blocks,
+    /// stopping when the leader's distance reaches it.
+    fn probabilities_words(
+        &self,
+        words: &[Word<Vec<char>>],
+        filtered_languages: FxHashSet<ScriptLanguage>,
+        mut ngram_sizes: &[NgramSize],
+        context: Option<&Context>,
+    ) -> (Vec<(ScriptLanguage, f64)>, usize) {
+        debug_assert!(!words.is_empty() && !ngram_sizes.is_empty());
+
+        /* if characters_count < ngram_length_range.start {
+            return filtered_languages
+                .into_iter()
+                .map(|l| (l, f64::NEG_INFINITY))
+                .collect();
+        } */
+
+        let wordgrams_enabled = *ngram_sizes.last().unwrap_safe_unchecked() == NgramSize::Word;
+        if wordgrams_enabled {
+            ngram_sizes = ngram_sizes.get_safe_unchecked(..ngram_sizes.len() - 1);
+        }
+
+        let mut probabilities = slang_arr_default::<(f64, usize)>();
+        // ngrams are deduplicated across blocks
+        let mut seen: Vec<FxHashSet<&[char]>> = vec![Default::default(); ngram_sizes.len()];
+        let mut consumed_words = 0;
+
+        let block_len = match self.early_exit_distance {
+            Some(_) => EARLY_EXIT_WORDS,
+            None => words.len(),
+        };
+        for block in words.chunks(block_len) {
+            for (&ngram_size, seen) in ngram_sizes.iter().zip(seen.iter_mut()) {
+                Self::probabilities_languages_ngrams(
+                    self.models_storage,
+                    ngram_size,
+                    ngram_iterator_with_seen(
+                        block.iter().map(|wd| wd.buf.as_ref()),
+                        ngram_size,
+                        seen,
+                    ),
+                    &filtered_languages,
+                    &mut probabilities,
+                );
+            }
+
+            if wordgrams_enabled {
+                Self::probabilities_languages_wordgrams(
+                    self.models_storage,
+                    block.iter().map(|wd| wd.buf.iter().collect::<String>()),
+                    &filtered_languages,
+                    &mut probabilities,
+                );
+            }
+
+            consumed_words += block.len();
+            if self.early_exit_distance.is_some_and(|early_exit_distance| {
+                self.leader_distance(&probabilities, &filtered_languages, context)
+                    >= early_exit_distance
+            }) {
+                break;
+            }
+        }
+
+        let mut probabilities_mean =
+            self.probabilities_mean(probabilities, filtered_languages, context);
+        probabilities_mean.sort_unstable_by(order_by_probability_and_lang);
+
+        (probabilities_mean, consumed_words)
+    }
+
+    /// Returns probabilities for the provided text with diagnostics (see [`DetectionResult`]).
+    /// Each value of `probabilities` is a logarithmic probability
+    /// between a negative infinity and 0.0.
+    ///
+    /// `probabilities` are sorted in a descending order.
     ///
     /// If only a single language is identified by `alphabet_detector`,
     /// the value 0.0 will be returned.
-    fn probabilities_extra(&self, text: &str) -> ProbabilitiesExtra {
+    #[inline]
+    pub fn detect(&self, text: &str) -> DetectionResult {
+        self.detect_in_context(text, None)
+    }
+
+    /// Same as [`detect`](Self::detect), but with priors of the `context` added
+    #[inline]
+    pub fn detect_with_context(&self, text: &str, context: &Context) -> DetectionResult {
+        self.detect_in_context(text, Some(context))
+    }
+
+    fn detect_in_context(&self, text: &str, context: Option<&Context>) -> DetectionResult {
         if text.is_empty() {
             return Default::default();
         }
@@ -242,80 +466,41 @@ impl<'m> Detector<'m> {
             return Default::default();
         }
 
+        let characters_count: usize = words.iter().map(|wd| wd.buf.len()).sum();
+
         if filtered_languages.len() == 1 {
             let lang = filtered_languages
                 .into_iter()
                 .next()
                 .unwrap_safe_unchecked();
 
-            return ProbabilitiesExtra {
+            return DetectionResult {
                 probabilities: vec![(lang, 0.0)],
                 words,
+                characters_count,
+                single_language: true,
+                ..Default::default()
             };
         }
 
-        let characters_count: usize = words.iter().map(|wd| wd.buf.len()).sum();
-
-        let mut ngram_sizes: &[NgramSize] = if characters_count < self.long_text_minlen {
-            &self.short_text_ngram_sizes
-        } else {
+        let long_text = characters_count >= self.long_text_minlen;
+        let ngram_sizes = if long_text {
             &self.long_text_ngram_sizes
+        } else {
+            &self.short_text_ngram_sizes
         };
-        debug_assert!(!ngram_sizes.is_empty());
-
-        /* if characters_count < ngram_length_range.start {
-            return filtered_languages
-                .into_iter()
-                .map(|l| (l, f64::NEG_INFINITY))
-                .collect();
-        } */
-
-        let wordgrams_enabled = *ngram_sizes.last().unwrap_safe_unchecked() == NgramSize::Word;
-        if wordgrams_enabled {
-            ngram_sizes = ngram_sizes.get_safe_unchecked(..ngram_sizes.len() - 1);
-        }
 
-        let mut probabilities = slang_arr_default::<(f64, usize)>();
-
-        for &ngram_size in ngram_sizes {
-            Self::probabilities_ngrams(
-                self.models_storage,
-                words.iter().map(|wd| wd.buf.as_ref()),
-                &filtered_languages,
-                ngram_size,
-                &mut probabilities,
-            );
-        }
-
-        if wordgrams_enabled {
-            Self::probabilities_languages_wordgrams(
-                self.models_storage,
-                words.iter().map(|wd| wd.buf.iter().collect::<String>()),
-                &filtered_languages,
-                &mut probabilities,
-            );
-
-            /* let mut dbg: Vec<_> = probabilities
-                .iter()
-                .enumerate()
-                .filter(|(_, d)| d.1 > 0)
-                .map(|(l, d)| (ScriptLanguage::transmute_from_usize(l), d))
-                .collect();
-            dbg.sort_by(|(_, d1), (_, d2)| (d2.0 / d2.1 as f64).total_cmp(&(d1.0 / d1.1 as f64)));
-            println!("OUTPUT {:?}", dbg); */
-        }
-
-        let mut probabilities_mean = Self::probabilities_mean(probabilities, filtered_languages);
-
-        probabilities_mean.sort_unstable_by(order_by_probability_and_lang);
-        /* println!(
-            "res {:?}",
-            &probabilities_mean[..probabilities_mean.len().min(5)]
-        ); */
+        let (probabilities, consumed_words) =
+            self.probabilities_words(&words, filtered_languages, ngram_sizes, context);
 
-        ProbabilitiesExtra {
-            probabilities: probabilities_mean,
+        DetectionResult {
+            probabilities,
             words,
+            consumed_words,
+            characters_count,
+            ngram_sizes: ngram_sizes.clone(),
+            long_text,
+            single_language: false,
         }
     }
 
@@ -328,7 +513,20 @@ impl<'m> Detector<'m> {
     /// the value 0.0 will be returned.
     #[inline]
     pub fn probabilities(&self, text: &str) -> Vec<(ScriptLanguage, f64)> {
-        self.probabilities_extra(text).probabilities
+        self.detect(text).probabilities
+    }
+
+    /// Returns probabilities for the provided text, with priors of the `context` added
+    /// (see [`Context`]), which matter most for short texts.
+    ///
+    /// Result is sorted by probabilities in a descending order.
+    #[inline]
+    pub fn probabilities_with_context(
+        &self,
+        text: &str,
+        context: &Context,
+    ) -> Vec<(ScriptLanguage, f64)> {
+        self.detect_with_context(text, context).probabilities
     }
 
     /// Returns probabilities for the provided text relative to other languages.
@@ -342,6 +540,32 @@ impl<'m> Detector<'m> {
         probabilities
     }
 
+    /// Returns calibrated probabilities for the provided text relative to other languages.
+    /// Each value is a number between 0.0 and 1.0.
+    ///
+    /// Logarithmic probabilities are scaled by the temperature of the text length
+    /// (see [`Calibration`](crate::calibration::Calibration)) stored in the models,
+    /// so the probability can be thresholded consistently across text lengths.
+    /// Equal to [`probabilities_relative`](Self::probabilities_relative) if models are not calibrated.
+    pub fn probabilities_calibrated(&self, text: &str) -> Vec<(ScriptLanguage, f64)> {
+        let DetectionResult {
+            mut probabilities,
+            characters_count,
+            ..
+        } = self.detect(text);
+
+        let temperature = self
+            .models_storage
+            .calibration
+            .temperature(characters_count);
+        probabilities
+            .iter_mut()
+            .for_each(|(_, p)| *p *= temperature);
+        transform_to_relative_probabilities(&mut probabilities);
+
+        probabilities
+    }
+
     /// Detects a top one language of the provided text.
     ///
     /// `minimum_distance` is a distance between a first and a second logarithmic probabilities,
@@ -353,24 +577,7 @@ impl<'m> Detector<'m> {
         text: &str,
         minimum_distance: f64,
     ) -> Option<ScriptLanguage> {
-        debug_assert!(minimum_distance >= 0.0, "Minimum distance must be >= 0.0");
-
-        let mut probabilities = self.probabilities(text).into_iter();
-
-        let (first_language, first_probability) = probabilities.next()?;
-        let Some((_, second_probability)) = probabilities.next() else {
-            return Some(first_language);
-        };
-
-        let probabilities_diff = first_probability - second_probability;
-        if probabilities_diff.is_nan()
-            || probabilities_diff < f64::EPSILON
-            || probabilities_diff < minimum_distance
-        {
-            return None;
-        }
-
-        Some(first_language)
+        top_one_or_none(self.probabilities(text), minimum_distance)
     }
 
     /// Detects a top one language of the provided text.
@@ -388,10 +595,11 @@ impl<'m> Detector<'m> {
     where
         F: FnOnce(Vec<Word<Vec<char>>>) -> f64,
     {
-        let ProbabilitiesExtra {
+        let DetectionResult {
             mut probabilities,
             words,
-        } = self.probabilities_extra(text);
+            ..
+        } = self.detect(text);
 
         let (_first_language, first_probability) = *probabilities.first()?;
 
@@ -463,6 +671,31 @@ fn order_by_probability_and_lang(
         .then_with(|| first.0.cmp(&second.0))
 }
 
+/// `probabilities` must be ordered
+fn top_one_or_none(
+    probabilities: Vec<(ScriptLanguage, f64)>,
+    minimum_distance: f64,
+) -> Option<ScriptLanguage> {
+    debug_assert!(minimum_distance >= 0.0, "Minimum distance must be >= 0.0");
+
+    let mut probabilities = probabilities.into_iter();
+
+    let (first_language, first_probability) = probabilities.next()?;
+    let Some((_, second_probability)) = probabilities.next() else {
+        return Some(first_language);
+    };
+
+    let probabilities_diff = first_probability - second_probability;
+    if probabilities_diff.is_nan()
+        || probabilities_diff < f64::EPSILON
+        || probabilities_diff < minimum_distance
+    {
+        return None;
+    }
+
+    Some(first_language)
+}
+
 /// `probabilities` must be ordered
 #[inline]
 fn transform_to_relative_probabilities(probabilities: &mut Vec<(ScriptLanguage, f64)>) {
@@ -488,29 +721,36 @@ fn transform_to_relative_probabilities(probabilities: &mut Vec<(ScriptLanguage,
         return;
     }
 
+    // shifted by the first (max) probability, so scaled probabilities don't underflow
     let mut denominator: f64 = 0.0;
     probabilities.iter_mut().for_each(|(_, p)| {
-        *p = p.exp();
+        *p = (*p - first_probability).exp();
         denominator += *p;
     });
 
-    if denominator == 0.0 {
-        // ::core::hint::cold_path();
-        if let Some((_, p)) = probabilities.first_mut() {
-            *p = 1.0
-        }
-        probabilities.truncate(1);
-
-        return;
-    }
-
     probabilities
         .iter_mut()
         .for_each(|(_, p)| *p /= denominator);
 }
 
+/// Result of [`Detector::detect`], with the data used to make the decision
 #[derive(Default, Debug, Clone)]
-struct ProbabilitiesExtra {
-    probabilities: Vec<(ScriptLanguage, f64)>,
-    words: Vec<Word<Vec<char>>>,
+pub struct DetectionResult {
+    /// Logarithmic probabilities, sorted in a descending order
+    pub probabilities: Vec<(ScriptLanguage, f64)>,
+    /// Words of the text, filtered by `alphabet_detector`
+    pub words: Vec<Word<Vec<char>>>,
+    /// Count of the first `words` scored by ngrams:
+    /// less than `words.len()` if stopped early (see [`DetectorBuilder::early_exit`]),
+    /// 0 if ngrams were not used
+    pub consumed_words: usize,
+    /// Characters count of `words`
+    pub characters_count: usize,
+    /// Ngram sizes used, empty if ngrams were not used
+    pub ngram_sizes: NgramSizes,
+    /// Long text ngram sizes were used (`characters_count >= long_text_minlen`),
+    /// otherwise short text ngram sizes
+    pub long_text: bool,
+    /// Only a single language was identified by `alphabet_detector`, so ngrams were not used
+    pub single_language: bool,
 }
@@ -0,0 +1,38 @@
+use super::{Detector, DetectorScratch};
+use alphabet_detector::ScriptLanguage;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+impl Detector<'_> {
+    /// Detects a top one language of each text in parallel,
+    /// same as [`detect_batch`](Self::detect_batch).
+    /// Results are in the order of `texts`.
+    pub fn par_detect_top_one<S>(&self, texts: &[S]) -> Vec<Option<ScriptLanguage>>
+    where
+        S: AsRef<str> + Sync,
+    {
+        texts
+            .par_iter()
+            .map_init(DetectorScratch::default, |scratch, text| {
+                self.probabilities_with_scratch(text.as_ref(), scratch)
+                    .first()
+                    .map(|(l, _)| *l)
+            })
+            .collect()
+    }
+
+    /// Returns probabilities of each text in parallel,
+    /// same as [`probabilities`](Self::probabilities).
+    /// Results are in the order of `texts`.
+    pub fn par_probabilities<S>(&self, texts: &[S]) -> Vec<Vec<(ScriptLanguage, f64)>>
+    where
+        S: AsRef<str> + Sync,
+    {
+        texts
+            .par_iter()
+            .map_init(DetectorScratch::default, |scratch, text| {
+                self.probabilities_with_scratch(text.as_ref(), scratch)
+                    .to_vec()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,176 @@
+use super::{order_by_probability_and_lang, Detector, EARLY_EXIT_WORDS};
+use crate::{
+    ngram_size::NgramSize,
+    ngrams::{ngram_iterator_with_keys, NgramKey},
+};
+use ::core::iter;
+use alphabet_detector::{
+    fulltext_filter_with_margin, slang_arr_default, ScriptLanguage, ScriptLanguageArr,
+};
+use debug_unsafe::{option::OptionUnwrapper, slice::SliceGetter};
+use rustc_hash::FxHashSet;
+
+/// Reusable buffers for [`Detector::probabilities_with_scratch`],
+/// so repeated calls don't allocate (except for the words of `alphabet_detector`).
+///
+/// Can be used with different detectors, but not concurrently.
+#[derive(Clone, Debug)]
+pub struct DetectorScratch {
+    filtered_languages: FxHashSet<ScriptLanguage>,
+    /// Only `filtered_languages` are not zeroed
+    probabilities: ScriptLanguageArr<(f64, usize)>,
+    /// Seen ngrams of each ngram size
+    seen: Vec<FxHashSet<NgramKey>>,
+    wordgram: String,
+    result: Vec<(ScriptLanguage, f64)>,
+}
+
+impl Default for DetectorScratch {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            filtered_languages: Default::default(),
+            probabilities: slang_arr_default(),
+            seen: Default::default(),
+            wordgram: Default::default(),
+            result: Default::default(),
+        }
+    }
+}
+
+impl DetectorScratch {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn clear(&mut self) {
+        for &language in self.filtered_languages.iter() {
+            *self.probabilities.get_safe_unchecked_mut(language as usize) = (0.0, 0);
+        }
+        self.filtered_languages.clear();
+        self.result.clear();
+    }
+}
+
+impl Detector<'_> {
+    /// Same as [`probabilities`](Self::probabilities) (including early exit),
+    /// but reuses buffers of `scratch`.
+    /// The result is valid until the next call with the same `scratch`.
+    pub fn probabilities_with_scratch<'s>(
+        &self,
+        text: &str,
+        scratch: &'s mut DetectorScratch,
+    ) -> &'s [(ScriptLanguage, f64)] {
+        scratch.clear();
+        if text.is_empty() {
+            return &scratch.result;
+        }
+
+        let DetectorScratch {
+            filtered_languages,
+            probabilities,
+            seen,
+            wordgram,
+            result,
+        } = scratch;
+
+        let (words, langs, _) = fulltext_filter_with_margin::<Vec<char>, 95>(text.char_indices());
+        filtered_languages.extend(
+            langs
+                .filter(|(l, _)| self.languages.contains(l))
+                .map(|(l, _)| l),
+        );
+
+        if words.is_empty() || filtered_languages.is_empty() {
+            return result;
+        }
+
+        if filtered_languages.len() == 1 {
+            let lang = *filtered_languages.iter().next().unwrap_safe_unchecked();
+            result.push((lang, 0.0));
+            return result;
+        }
+
+        let characters_count: usize = words.iter().map(|wd| wd.buf.len()).sum();
+        let ngram_sizes = if characters_count >= self.long_text_minlen {
+            &self.long_text_ngram_sizes
+        } else {
+            &self.short_text_ngram_sizes
+        };
+
+        if seen.len() < ngram_sizes.len() {
+            seen.resize_with(ngram_sizes.len(), Default::default);
+        }
+        seen.iter_mut().for_each(|seen| seen.clear());
+
+        // same blocks as `probabilities_words`
+        let block_len = match self.early_exit_distance {
+            Some(_) => EARLY_EXIT_WORDS,
+            None => words.len(),
+        };
+        for block in words.chunks(block_len) {
+            for (&ngram_size, seen) in ngram_sizes.iter().zip(seen.iter_mut()) {
+                if ngram_size == NgramSize::Word {
+                    for word in block.iter() {
+                        wordgram.clear();
+                        wordgram.extend(word.buf.iter());
+                        Self::probabilities_languages_wordgrams(
+                            self.models_storage,
+                            iter::once(wordgram.as_str()),
+                            filtered_languages,
+                            probabilities,
+                        );
+                    }
+                } else {
+                    Self::probabilities_languages_ngrams(
+                        self.models_storage,
+                        ngram_size,
+                        ngram_iterator_with_keys(
+                            block.iter().map(|wd| wd.buf.as_ref()),
+                            ngram_size,
+                            seen,
+                        ),
+                        filtered_languages,
+                        probabilities,
+                    );
+                }
+            }
+
+            if self.early_exit_distance.is_some_and(|early_exit_distance| {
+                self.leader_distance(probabilities, filtered_languages, None) >= early_exit_distance
+            }) {
+                break;
+            }
+        }
+
+        result.extend(filtered_languages.iter().map(|&language| {
+            (
+                language,
+                self.language_mean(
+                    language,
+                    *probabilities.get_safe_unchecked(language as usize),
+                    None,
+                ),
+            )
+        }));
+        result.sort_unstable_by(order_by_probability_and_lang);
+
+        result
+    }
+
+    /// Detects a top one language of each text, same as [`detect_top_one_raw`](Self::detect_top_one_raw),
+    /// reusing buffers between texts (see [`DetectorScratch`]).
+    pub fn detect_batch(&self, texts: &[&str]) -> Vec<Option<ScriptLanguage>> {
+        let mut scratch = DetectorScratch::default();
+
+        texts
+            .iter()
+            .map(|text| {
+                self.probabilities_with_scratch(text, &mut scratch)
+                    .first()
+                    .map(|(l, _)| *l)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,227 @@
+use super::{order_by_probability_and_lang, transform_to_relative_probabilities, Detector};
+use ::core::{ops::Range, slice};
+use alphabet_detector::{fulltext_filter_with_margin, ScriptLanguage, Word};
+use debug_unsafe::{option::OptionUnwrapper, slice::SliceGetter};
+use rustc_hash::FxHashSet;
+
+/// Logarithmic penalty of a language switch between adjacent words,
+/// so single ambiguous words don't fragment segments
+const SWITCH_PENALTY: f64 = 3.0;
+/// Logarithmic probability of a language, which is not probable for a word
+const WORD_MIN_PROBABILITY: f64 = -9.0;
+
+/// Contiguous part of the text in a single language
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    /// Byte range in the text, from the start of the first word to the end of the last word
+    pub range: Range<usize>,
+    pub language: ScriptLanguage,
+    /// Mean relative probability of `language` for the words of the segment,
+    /// a number between 0.0 and 1.0
+    pub confidence: f64,
+}
+
+/// Word, labelled with a language
+pub(super) struct LabelledWord {
+    pub(super) word: Word<Vec<char>>,
+    pub(super) language: ScriptLanguage,
+    /// Relative probability of `language` for the word
+    pub(super) probability: f64,
+}
+
+impl Detector<'_> {
+    /// Relative probabilities of a single word.
+    /// Only languages with the most matching characters are compared.
+    fn word_probabilities(
+        &self,
+        word: &Word<Vec<char>>,
+        filtered_languages: &[ScriptLanguage],
+    ) -> Vec<(ScriptLanguage, f64)> {
+        let langs_cnt =
+            |language: &ScriptLanguage| *word.langs_cnt.get_safe_unchecked(*language as usize);
+        let max_cnt = filtered_languages
+            .iter()
+            .map(langs_cnt)
+            .max()
+            .unwrap_or_default();
+        let languages: FxHashSet<_> = filtered_languages
+            .iter()
+            .copied()
+            .filter(|language| langs_cnt(language) == max_cnt)
+            .collect();
+
+        let mut probabilities = if languages.len() == 1 {
+            vec![(languages.into_iter().next().unwrap_safe_unchecked(), 0.0)]
+        } else {
+            self.probabilities_words(
+                slice::from_ref(word),
+                languages,
+                &self.short_text_ngram_sizes,
+                None,
+            )
+            .0
+        };
+        transform_to_relative_probabilities(&mut probabilities);
+
+        probabilities
+    }
+
+    /// Returns words of the text, each labelled with a language.
+    ///
+    /// Labels are smoothed by choosing the most probable sequence of languages
+    /// (Viterbi algorithm), where each language switch is penalized.
+    pub(super) fn labelled_words(&self, text: &str) -> Vec<LabelledWord> {
+        let (words, langs, _) = fulltext_filter_with_margin::<Vec<char>, 95>(text.char_indices());
+        let filtered_languages: Vec<_> = langs
+            .filter(|(l, _)| self.languages.contains(l))
+            .map(|(l, _)| l)
+            .collect();
+
+        if words.is_empty() || filtered_languages.is_empty() {
+            return Default::default();
+        }
+
+        let words_probabilities: Vec<_> = words
+            .iter()
+            .map(|word| self.word_probabilities(word, &filtered_languages))
+            .collect();
+
+        // logarithmic relative probabilities, `filtered_languages` per word
+        let emissions: Vec<f64> = words_probabilities
+            .iter()
+            .flat_map(|probabilities| {
+                filtered_languages.iter().map(|language| {
+                    probabilities
+                        .iter()
+                        .find(|(l, _)| l == language)
+                        .map_or(WORD_MIN_PROBABILITY, |(_, p)| {
+                            p.ln().max(WORD_MIN_PROBABILITY)
+                        })
+                })
+            })
+            .collect();
+        let mut emissions = emissions.chunks_exact(filtered_languages.len());
+
+        let argmax = |scores: &[f64]| {
+            scores
+                .iter()
+                .enumerate()
+                .fold((0, f64::NEG_INFINITY), |best, (i, &score)| {
+                    if score > best.1 {
+                        (i, score)
+                    } else {
+                        best
+                    }
+                })
+        };
+
+        let mut scores = emissions.next().unwrap_safe_unchecked().to_vec();
+        // previous language index of each language, for each next word
+        let mut backtrack: Vec<Vec<usize>> = Vec::with_capacity(words.len() - 1);
+        for emission in emissions {
+            let (best_i, best) = argmax(&scores);
+            let switch_score = best - SWITCH_PENALTY;
+            let mut back = Vec::with_capacity(scores.len());
+            for (score, e) in scores.iter_mut().zip(emission) {
+                if *score >= switch_score {
+                    back.push(back.len());
+                    *score += e;
+                } else {
+                    back.push(best_i);
+                    *score = switch_score + e;
+                }
+            }
+            backtrack.push(back);
+        }
+
+        let mut language_i = argmax(&scores).0;
+        let mut labels = vec![language_i; words.len()];
+        for (label, back) in labels.iter_mut().rev().skip(1).zip(backtrack.iter().rev()) {
+            language_i = *back.get_safe_unchecked(language_i);
+            *label = language_i;
+        }
+
+        words
+            .into_iter()
+            .zip(labels)
+            .zip(words_probabilities)
+            .map(|((word, language_i), probabilities)| {
+                let language = *filtered_languages.get_safe_unchecked(language_i);
+                let probability = probabilities
+                    .into_iter()
+                    .find(|(l, _)| *l == language)
+                    .map_or(0.0, |(_, p)| p);
+                LabelledWord {
+                    word,
+                    language,
+                    probability,
+                }
+            })
+            .collect()
+    }
+
+    /// Splits mixed-language text into contiguous segments, each in a single language.
+    ///
+    /// Words are detected separately, then smoothed,
+    /// so single ambiguous words don't fragment segments.
+    /// Text between words (spaces, punctuation) is not covered by segments.
+    pub fn detect_segments(&self, text: &str) -> Vec<Segment> {
+        let mut segments: Vec<(Segment, usize)> = Vec::new();
+        for LabelledWord {
+            word,
+            language,
+            probability,
+        } in self.labelled_words(text)
+        {
+            match segments.last_mut() {
+                Some((segment, words_cnt)) if segment.language == language => {
+                    segment.range.end = word.range.end;
+                    segment.confidence += probability;
+                    *words_cnt += 1;
+                }
+                _ => segments.push((
+                    Segment {
+                        range: word.range,
+                        language,
+                        confidence: probability,
+                    },
+                    1,
+                )),
+            }
+        }
+
+        segments
+            .into_iter()
+            .map(|(mut segment, words_cnt)| {
+                segment.confidence /= words_cnt as f64;
+                segment
+            })
+            .collect()
+    }
+
+    /// Returns fractions of the text in each language, by characters of words.
+    /// Each value is a number between 0.0 and 1.0, values sum to 1.0.
+    ///
+    /// Result is sorted by fractions in a descending order.
+    pub fn language_shares(&self, text: &str) -> Vec<(ScriptLanguage, f64)> {
+        let labelled_words = self.labelled_words(text);
+
+        let mut characters_count = 0;
+        let mut shares: Vec<(ScriptLanguage, f64)> = Vec::new();
+        for LabelledWord { word, language, .. } in labelled_words {
+            let word_characters_count = word.buf.len();
+            characters_count += word_characters_count;
+            match shares.iter_mut().find(|(l, _)| *l == language) {
+                Some((_, share)) => *share += word_characters_count as f64,
+                None => shares.push((language, word_characters_count as f64)),
+            }
+        }
+
+        shares
+            .iter_mut()
+            .for_each(|(_, share)| *share /= characters_count as f64);
+        shares.sort_unstable_by(order_by_probability_and_lang);
+
+        shares
+    }
+}
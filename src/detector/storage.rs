@@ -1,166 +1,836 @@
-use crate::bin_storage::{ArchivedBinStorage, StorageNgrams, StorageNgramsArr};
-#[cfg(test)]
-use crate::model::Model;
+use crate::{
+    bin_storage::{
+        checksum, ArchivedBinStorage, ArchivedLegacyBinStorage, ArchivedQuantized, BinStorage,
+        LegacyBinStorage, ModelsMetadata, NgramScore, ProbabilityEncoding, StorageNgrams,
+        StorageNgramsArr, CHECKSUM_RANGE, FORMAT_VERSION, HEADER_LEN, MAGIC,
+    },
+    calibration::{ArchivedCalibration, Calibration},
+    model::Model,
+    NgramSize,
+};
 use ::std::{
     env, fmt,
-    fs::{self, File},
-    io::{self, copy, BufReader, BufWriter},
-    path::PathBuf,
+    fs::{self, File, OpenOptions},
+    io::{self, copy, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
 };
-use alphabet_detector::{ScriptLanguage, ScriptLanguageArr};
+use alphabet_detector::{EnumCount, ScriptLanguage, ScriptLanguageArr};
 use brotli_decompressor::Decompressor;
+use debug_unsafe::slice::SliceGetter;
 use memmap2::Mmap;
-use reqwest::blocking::get;
-use rkyv::Archive;
+use rkyv::{util::AlignedVec, Archive, Archived};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
-pub(super) type NgramModel = <StorageNgrams as Archive>::Archived;
+pub(super) type NgramModel<P = f64> = <StorageNgrams<P> as Archive>::Archived;
 type NgramModelArr = <StorageNgramsArr as Archive>::Archived;
 
-pub struct ModelsStorage<'m> {
-    #[allow(unused)]
-    mmap: Mmap,
-    pub(super) langs_ngram_min_probability: &'m <ScriptLanguageArr<f64> as Archive>::Archived,
-    pub(super) ngrams: &'m NgramModelArr,
-    pub(super) wordgrams: &'m NgramModel,
-    pub(super) wordgram_min_probability: f64,
+/// Probability, which can be quantized
+pub(super) trait Probability: Archive {
+    fn dequantize(archived: &Self::Archived, table: &[Archived<f64>]) -> f64;
 }
 
-impl fmt::Debug for ModelsStorage<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("ModelsStorage")
-            .field("wordgram_min_probability", &self.wordgram_min_probability)
-            .finish_non_exhaustive()
+impl Probability for f64 {
+    #[inline(always)]
+    fn dequantize(archived: &Self::Archived, _table: &[Archived<f64>]) -> f64 {
+        archived.to_native()
     }
 }
 
-impl<'m> ModelsStorage<'m> {
-    pub const FILE_NAME: &'static str = "langram_models.bin";
+impl Probability for u16 {
+    #[inline(always)]
+    fn dequantize(archived: &Self::Archived, table: &[Archived<f64>]) -> f64 {
+        table
+            .get_safe_unchecked(archived.to_native() as usize)
+            .to_native()
+    }
+}
+
+impl Probability for u8 {
+    #[inline(always)]
+    fn dequantize(archived: &Self::Archived, table: &[Archived<f64>]) -> f64 {
+        table.get_safe_unchecked(*archived as usize).to_native()
+    }
+}
+
+/// Code generic over the probability encoding of an ngram model,
+/// see [`ModelsStorage::with_ngram_model`]
+pub(super) trait NgramModelFn {
+    type Output;
+
+    fn call<P: Probability>(
+        self,
+        ngram_model: &NgramModel<P>,
+        table: &[Archived<f64>],
+    ) -> Self::Output;
+}
+
+#[cfg(feature = "download")]
+const MODELS_URL: &str =
+    "https://github.com/RoDmitry/langram_models/releases/download/v0.11/langram_models.bin.br";
+/// SHA-256 of the decompressed models file of `MODELS_URL`, downloads are always verified with it
+// TODO: pin the digest of the released file (`brotli -dc langram_models.bin.br | sha256sum`)
+#[cfg(feature = "download")]
+const MODELS_SHA256: [u8; 32] = [0; 32];
+
+/// Options of models file lookup.
+///
+/// The models file is looked up in [`path`](Self::path).
+/// The `.br` compressed models file from `path` is decompressed into [`cache_dir`](Self::cache_dir),
+/// and reused while the `.br` file is not changed.
+///
+/// Never touches the network, unless `download` feature is enabled
+/// and [`download`](Self::download) is set.
+#[derive(Clone, Debug, Default)]
+pub struct ModelsStorageOptions {
+    path: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    sha256: Option<[u8; 32]>,
+    #[cfg(feature = "download")]
+    download: bool,
+}
+
+impl ModelsStorageOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Models file, or a directory with the models file (or it's `.br` compressed version).
+    ///
+    /// If not set, uses `LANGRAM_MODELS_PATH`, or the directory of the executable.
+    /// Unlike an explicit `path`, a missing `LANGRAM_MODELS_PATH` is looked up as a directory,
+    /// so it falls back to the compressed models file, and download.
+    #[inline]
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Writable directory, where the decompressed, downloaded
+    /// or converted (from the `v0.11` format) models file is saved.
+    /// The models file is named by it's source (path, size and modification time of the `.br` file,
+    /// or the download URL), and is reused on next opens of the same source.
+    ///
+    /// If not set, uses `LANGRAM_CACHE_DIR`, or `langram` in the user cache directory
+    /// (`XDG_CACHE_HOME`, `~/.cache`, `~/Library/Caches` or `%LOCALAPPDATA%`),
+    /// or the models directory if there is no user cache directory.
+    #[inline]
+    pub fn cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self
+    }
+
+    /// Expected SHA-256 of the (decompressed) models file.
+    ///
+    /// Checked before the decompressed or downloaded file is renamed into place,
+    /// and when an existing file is opened.
+    #[inline]
+    pub fn sha256(mut self, sha256: [u8; 32]) -> Self {
+        self.sha256 = Some(sha256);
+        self
+    }
 
+    /// Download models from GitHub releases, if not found locally.
+    /// Downloaded models are always verified with the SHA-256 of the release.
+    #[cfg(feature = "download")]
     #[inline]
-    fn get_file() -> Result<File, ModelsStorageError> {
-        let path = match env::var("LANGRAM_MODELS_PATH") {
-            Ok(path_str) => {
-                let mut path = PathBuf::from(path_str);
-                if path.is_file() {
-                    if let Ok(file) = File::open(&path) {
-                        return Ok(file);
-                    }
-                    path.pop();
+    pub fn download(mut self, download: bool) -> Self {
+        self.download = download;
+        self
+    }
+
+    #[inline]
+    pub fn open<'m>(&self) -> Result<ModelsStorage<'m>, ModelsStorageError> {
+        let (file, verify) = self.get_file()?;
+
+        let mmap = unsafe { Mmap::map(&file) }.map_err(ModelsStorageError::Mmap)?;
+        match verify {
+            Verify::Sha256 => self.verify_sha256(&mmap)?,
+            // the source was verified when the cached file was written
+            Verify::Cached if self.sha256.is_some() => ModelsStorage::verify_bytes(&mmap)?,
+            Verify::Cached | Verify::Done => {}
+        }
+
+        ModelsStorage::from_mmap(mmap)
+    }
+
+    /// Checks the pinned SHA-256, if set
+    #[inline]
+    fn verify_sha256(&self, bytes: &[u8]) -> Result<(), ModelsStorageError> {
+        self.sha256
+            .map_or(Ok(()), |expected| verify_sha256(bytes, expected))
+    }
+
+    /// Returns the models file, and what is left to verify
+    fn get_file(&self) -> Result<(File, Verify), ModelsStorageError> {
+        let (path, explicit) = match &self.path {
+            Some(path) => (path.clone(), true),
+            None => match env::var_os("LANGRAM_MODELS_PATH") {
+                Some(path) => (PathBuf::from(path), false),
+                None => {
+                    let mut path_near =
+                        env::current_exe().map_err(ModelsStorageError::CurrentExe)?;
+                    path_near.pop();
+                    (path_near, false)
                 }
-                path
-            }
-            Err(_) => {
-                let mut path_near = env::current_exe().map_err(ModelsStorageError::CurrentExe)?;
-                path_near.pop();
-                path_near
-            }
+            },
         };
 
-        let file_path = path.join(Self::FILE_NAME);
-        if let Ok(file) = File::open(&file_path) {
-            return Ok(file);
+        let cache_dir = || {
+            self.cache_dir
+                .clone()
+                .or_else(default_cache_dir)
+                .unwrap_or_else(|| match path.parent() {
+                    // models file
+                    Some(parent) if path.is_file() => parent.to_owned(),
+                    _ => path.clone(),
+                })
+        };
+
+        // missing `LANGRAM_MODELS_PATH` is a directory, so it falls back to `.br` and download
+        if !path.is_dir() && (explicit || path.exists()) {
+            let file =
+                File::open(&path).map_err(|_| ModelsStorageError::NotFound(vec![path.clone()]))?;
+            return self.plain_file(&path, file, &cache_dir());
         }
 
-        let part_file_path = path.join(concat_const::concat!(ModelsStorage::FILE_NAME, ".part"));
-        if part_file_path.exists() {
-            fs::remove_file(&part_file_path).map_err(ModelsStorageError::ModelsPartFileRemove)?;
+        let file_path = path.join(ModelsStorage::FILE_NAME);
+        if let Ok(file) = File::open(&file_path) {
+            return self.plain_file(&file_path, file, &cache_dir());
         }
 
-        let file =
-            File::create_new(&part_file_path).map_err(ModelsStorageError::ModelsPartFileCreate)?;
-        let mut writer = BufWriter::new(file);
+        let cache_dir = cache_dir();
 
         let compressed_file_path =
             path.join(concat_const::concat!(ModelsStorage::FILE_NAME, ".br"));
-        if let Ok(compressed_file) = File::open(compressed_file_path) {
-            let reader = BufReader::new(compressed_file);
+        if let Ok(compressed_file) = File::open(&compressed_file_path) {
+            let cached_file = compressed_file
+                .metadata()
+                .map(|metadata| {
+                    CachedFile::versioned(&compressed_file_path, &metadata, self.sha256)
+                })
+                .map_err(|_| ModelsStorageError::NotFound(vec![compressed_file_path.clone()]))?;
+            return Self::materialize(&cache_dir, &cached_file, |file_path| {
+                self.write_into(
+                    file_path,
+                    Decompressor::new(BufReader::new(compressed_file), 4096),
+                    None,
+                )
+            });
+        }
 
-            // Brotli decompressor
-            let mut decompressor = Decompressor::new(reader, 4096);
-            // Stream decompressed bytes into output file
-            copy(&mut decompressor, &mut writer).map_err(ModelsStorageError::StreamDecompressor)?;
-        } else {
+        #[cfg(feature = "download")]
+        if self.download {
+            return self.download_into(&cache_dir, MODELS_SHA256, || {
+                reqwest::blocking::get(MODELS_URL).map_err(ModelsStorageError::Download)
+            });
+        }
+
+        Err(ModelsStorageError::NotFound(vec![
+            file_path,
+            compressed_file_path,
+        ]))
+    }
+
+    /// Downloads compressed models from the `response` into `cache_dir`,
+    /// unless they are already downloaded. Verified with the pinned `sha256` of the release.
+    #[cfg(feature = "download")]
+    fn download_into<R: Read>(
+        &self,
+        cache_dir: &Path,
+        sha256: [u8; 32],
+        response: impl FnOnce() -> Result<R, ModelsStorageError>,
+    ) -> Result<(File, Verify), ModelsStorageError> {
+        let cached_file = CachedFile::new(MODELS_URL.as_bytes(), &sha256, self.sha256);
+        Self::materialize(cache_dir, &cached_file, |file_path| {
             println!("Downloading langram models...");
-            let response = get(
-                "https://github.com/RoDmitry/langram_models/releases/download/v0.11/langram_models.bin.br"
-            ).map_err(ModelsStorageError::Download)?;
-
-            // Brotli decompressor
-            let mut decompressor = Decompressor::new(response, 64 * 1024);
-            // Stream decompressed bytes into output file
-            copy(&mut decompressor, &mut writer).map_err(ModelsStorageError::StreamDecompressor)?;
+            let file = self.write_into(
+                file_path,
+                Decompressor::new(response()?, 64 * 1024),
+                Some(sha256),
+            )?;
             println!("Downloaded langram models");
+
+            Ok(file)
+        })
+    }
+
+    /// Models file without the header (`v0.11` models release) is converted once
+    /// into the current format in `cache_dir`, other models files are used as is
+    fn plain_file(
+        &self,
+        path: &Path,
+        file: File,
+        cache_dir: &Path,
+    ) -> Result<(File, Verify), ModelsStorageError> {
+        let mmap = unsafe { Mmap::map(&file) }.map_err(ModelsStorageError::Mmap)?;
+        if mmap.starts_with(&MAGIC) {
+            return Ok((file, Verify::Sha256));
+        }
+
+        let cached_file = file
+            .metadata()
+            .map(|metadata| CachedFile::versioned(path, &metadata, self.sha256))
+            .map_err(|_| ModelsStorageError::NotFound(vec![path.to_owned()]))?;
+        Self::materialize(cache_dir, &cached_file, |file_path| {
+            self.write_into(file_path, &mmap[..], None)
+        })
+    }
+
+    /// Writes the `cached_file` into `cache_dir` using `write`,
+    /// unless it's already written (also by a concurrent process)
+    fn materialize(
+        cache_dir: &Path,
+        cached_file: &CachedFile,
+        write: impl FnOnce(&Path) -> Result<File, ModelsStorageError>,
+    ) -> Result<(File, Verify), ModelsStorageError> {
+        let cached_file_path = cache_dir.join(&cached_file.name);
+        if let Ok(file) = File::open(&cached_file_path) {
+            return Ok((file, Verify::Cached));
+        }
+
+        fs::create_dir_all(cache_dir).map_err(ModelsStorageError::CacheDirCreate)?;
+
+        // Only one process materializes the models file, others wait and reuse it
+        let _lock = Self::lock(cache_dir);
+        if let Ok(file) = File::open(&cached_file_path) {
+            return Ok((file, Verify::Cached));
         }
 
+        let file = write(&cached_file_path)?;
+        cached_file.remove_outdated(cache_dir);
+
+        Ok((file, Verify::Done))
+    }
+
+    /// Exclusive lock of the models file materialization in `dir`, released on drop.
+    ///
+    /// `None` if locking is not possible (read-only dir, unsupported platform),
+    /// then materialization still is safe, because of unique part files and atomic rename.
+    fn lock(dir: &Path) -> Option<File> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(concat_const::concat!(ModelsStorage::FILE_NAME, ".lock")))
+            .ok()?;
+        file.lock().ok()?;
+
+        Some(file)
+    }
+
+    /// Writes the models stream (decompressed) into the models file at `file_path`.
+    /// The file is verified (also with `sha256`, if set), and converted into the current format,
+    /// before it is renamed into place.
+    fn write_into(
+        &self,
+        file_path: &Path,
+        reader: impl Read,
+        sha256: Option<[u8; 32]>,
+    ) -> Result<File, ModelsStorageError> {
+        // Unique per process and call, so concurrent writers never share a part file
+        static PART_ID: AtomicUsize = AtomicUsize::new(0);
+        let mut part_file_path = file_path.as_os_str().to_owned();
+        part_file_path.push(format!(
+            ".{}.{}.part",
+            process::id(),
+            PART_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let part_file_path = PathBuf::from(part_file_path);
+        if part_file_path.exists() {
+            fs::remove_file(&part_file_path).map_err(ModelsStorageError::ModelsPartFileRemove)?;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&part_file_path)
+            .map_err(ModelsStorageError::ModelsPartFileCreate)?;
+
+        let file = match self.write_part(file, reader, sha256) {
+            Ok(file) => file,
+            Err(e) => {
+                fs::remove_file(&part_file_path)
+                    .map_err(ModelsStorageError::ModelsPartFileRemove)?;
+                return Err(e);
+            }
+        };
+
+        // Atomically replaces the models file, if it was written by a concurrent process
+        fs::rename(part_file_path, file_path).map_err(ModelsStorageError::ModelsPartFileRename)?;
+
+        Ok(file)
+    }
+
+    /// Writes into the part file, verifies it,
+    /// and converts the models binary without the header into the current format
+    fn write_part(
+        &self,
+        file: File,
+        mut reader: impl Read,
+        sha256: Option<[u8; 32]>,
+    ) -> Result<File, ModelsStorageError> {
+        let mut writer = BufWriter::new(file);
+
+        // Stream bytes into output file
+        copy(&mut reader, &mut writer).map_err(ModelsStorageError::StreamDecompressor)?;
+
         // Also ensures all buffered data is written
         let file = writer
             .into_inner()
             .map_err(ModelsStorageError::WriterIntoInner)?;
 
+        let mmap = unsafe { Mmap::map(&file) }.map_err(ModelsStorageError::Mmap)?;
+        ModelsStorage::verify_bytes(&mmap)?;
+        self.verify_sha256(&mmap)?;
+        if let Some(expected) = sha256 {
+            verify_sha256(&mmap, expected)?;
+        }
+        let converted = if mmap.starts_with(&MAGIC) {
+            None
+        } else {
+            Some(ModelsStorage::convert_legacy(&mmap)?)
+        };
+        drop(mmap);
+
+        let mut file = file;
+        if let Some(bytes) = converted {
+            file.set_len(0)
+                .and_then(|_| file.seek(SeekFrom::Start(0)))
+                .and_then(|_| file.write_all(&bytes))
+                .map_err(ModelsStorageError::ConvertedWrite)?;
+        }
+
         // Optional but safer against power loss
         file.sync_all().map_err(ModelsStorageError::FileSync)?;
 
-        fs::rename(part_file_path, file_path).map_err(ModelsStorageError::ModelsPartFileRename)?;
-
         Ok(file)
     }
+}
+
+/// What is left to verify of the opened models file
+enum Verify {
+    /// The pinned SHA-256, if set
+    Sha256,
+    /// Cached file, which source was verified on write
+    Cached,
+    /// Just written and verified
+    Done,
+}
+
+/// Decompressed or downloaded models file in the cache directory,
+/// named by it's source and version, so different sources never share it,
+/// and a changed source is materialized again
+struct CachedFile {
+    /// Common for all versions of the source
+    prefix: String,
+    name: String,
+}
+
+impl CachedFile {
+    /// Also versioned by the pinned SHA-256 of the source, which is verified on write
+    fn new(source: &[u8], version: &[u8], sha256: Option<[u8; 32]>) -> Self {
+        let stem = ModelsStorage::FILE_NAME.trim_end_matches(".bin");
+        let prefix = format!("{stem}.{}.", short_digest(source));
+        let mut version = version.to_vec();
+        if let Some(sha256) = sha256 {
+            version.extend_from_slice(&sha256);
+        }
+        let name = format!("{prefix}{}.bin", short_digest(&version));
+
+        Self { prefix, name }
+    }
+
+    /// Of the models source file, versioned by it's size and modification time
+    fn versioned(path: &Path, metadata: &fs::Metadata, sha256: Option<[u8; 32]>) -> Self {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(::std::time::UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        let mut version = metadata.len().to_le_bytes().to_vec();
+        version.extend_from_slice(&modified.as_nanos().to_le_bytes());
+
+        Self::new(path.as_os_str().as_encoded_bytes(), &version, sha256)
+    }
+
+    /// Removes other versions of the source from `dir`
+    fn remove_outdated(&self, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            if file_name.starts_with(&self.prefix)
+                && file_name.ends_with(".bin")
+                && file_name != self.name
+            {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+/// Hex of the first 8 bytes of SHA-256
+fn short_digest(bytes: &[u8]) -> String {
+    display_hex(&Sha256::digest(bytes)[..8])
+}
+
+/// Owner of the models bytes
+enum ModelsData {
+    Mmap(#[allow(unused)] Mmap),
+    Vec(#[allow(unused)] AlignedVec),
+    Borrowed,
+}
+
+/// Models binary of the `v0.11` models release (without the header) is converted
+/// into the current format once, and saved into the cache directory by
+/// [`ModelsStorageOptions::open`] (see [`ModelsStorageOptions::cache_dir`]).
+/// [`from_vec`](Self::from_vec) and [`from_bytes`](Self::from_bytes) convert it in memory
+/// on each load, which is slower. It can be converted once, and saved with
+/// [`to_bin_storage`](Self::to_bin_storage) and [`BinStorage::to_bytes`].
+pub struct ModelsStorage<'m> {
+    #[allow(unused)]
+    data: ModelsData,
+    /// Whole models binary, including the header
+    bytes: &'m [u8],
+    bin_storage: &'m ArchivedBinStorage,
+    pub(super) langs_ngram_min_probability: &'m <ScriptLanguageArr<f64> as Archive>::Archived,
+    pub(super) ngrams: &'m NgramModelArr,
+    pub(super) wordgrams: &'m NgramModel,
+    pub(super) wordgram_min_probability: f64,
+    pub(super) langs_ngram_scores:
+        &'m <ScriptLanguageArr<[NgramScore; NgramSize::COUNT]> as Archive>::Archived,
+    /// Used instead of `ngrams` and `wordgrams` if not `None`
+    pub(super) quantized: &'m ArchivedQuantized,
+    pub(super) calibration: &'m ArchivedCalibration,
+}
+
+impl fmt::Debug for ModelsStorage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModelsStorage")
+            .field("wordgram_min_probability", &self.wordgram_min_probability)
+            .finish_non_exhaustive()
+    }
+}
 
+impl<'m> ModelsStorage<'m> {
+    pub const FILE_NAME: &'static str = "langram_models.bin";
+
+    /// Looks for the models file in `LANGRAM_MODELS_PATH`, or near the executable.
+    /// Decompresses `.br` models file into the cache directory if found
+    /// (see [`ModelsStorageOptions::cache_dir`]).
+    ///
+    /// With `download` feature enabled, downloads models if not found.
     #[inline]
     pub fn new() -> Result<Self, ModelsStorageError> {
-        let file = Self::get_file()?;
+        let options = ModelsStorageOptions::new();
+        #[cfg(feature = "download")]
+        let options = options.download(true);
 
-        let mmap = unsafe { Mmap::map(&file) }.map_err(ModelsStorageError::Mmap)?;
+        options.open()
+    }
 
-        Self::from_mmap(mmap)
+    /// Opens models from `path` (file or directory). Never touches the network.
+    #[inline]
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, ModelsStorageError> {
+        ModelsStorageOptions::new().path(path).open()
     }
 
     #[inline]
     fn from_mmap(mmap: Mmap) -> Result<Self, ModelsStorageError> {
         // SAFETY: slice has the same lifetime as mmap
         let slice: &'m [u8] = unsafe { ::core::mem::transmute::<&[u8], &'m [u8]>(mmap.as_ref()) };
+        Self::from_slice(slice, ModelsData::Mmap(mmap))
+    }
+
+    /// Loads models from an owned buffer, e.g. read from a database blob.
+    #[inline]
+    pub fn from_vec(vec: AlignedVec) -> Result<Self, ModelsStorageError> {
+        // SAFETY: slice has the same lifetime as vec, heap buffer is not moved with it
+        let slice: &'m [u8] = unsafe { ::core::mem::transmute::<&[u8], &'m [u8]>(vec.as_slice()) };
+        Self::from_slice(slice, ModelsData::Vec(vec))
+    }
+
+    /// Loads models from a borrowed buffer, e.g. `include_bytes!` or WASM memory.
+    ///
+    /// `bytes` must be aligned to [`AlignedVec::ALIGNMENT`],
+    /// otherwise they are copied into an [`AlignedVec`].
+    #[inline]
+    pub fn from_bytes(bytes: &'m [u8]) -> Result<Self, ModelsStorageError> {
+        if bytes.as_ptr().align_offset(<AlignedVec>::ALIGNMENT) != 0 {
+            let mut vec = AlignedVec::with_capacity(bytes.len());
+            vec.extend_from_slice(bytes);
+            return Self::from_vec(vec);
+        }
+
+        Self::from_slice(bytes, ModelsData::Borrowed)
+    }
+
+    /// Checks the header, returns the archive
+    fn split_header(bytes: &[u8]) -> Result<&[u8], ModelsStorageError> {
+        let found = bytes
+            .strip_prefix(&MAGIC)
+            .and_then(|rest| rest.first_chunk::<4>())
+            .map(|version| u32::from_le_bytes(*version))
+            .unwrap_or(0);
+        if found != FORMAT_VERSION {
+            return Err(ModelsStorageError::FormatVersion {
+                expected: FORMAT_VERSION,
+                found,
+            });
+        }
+
+        Ok(bytes.get(HEADER_LEN..).unwrap_or_default())
+    }
+
+    /// Accesses the models binary without the header (`v0.11` models release)
+    #[inline]
+    fn access_legacy(bytes: &[u8]) -> Option<&ArchivedLegacyBinStorage> {
+        rkyv::access::<ArchivedLegacyBinStorage, rkyv::rancor::Error>(bytes).ok()
+    }
+
+    /// Converts the models binary without the header into the current format
+    fn convert_legacy(bytes: &[u8]) -> Result<AlignedVec, ModelsStorageError> {
+        let legacy = rkyv::from_bytes::<LegacyBinStorage, rkyv::rancor::Error>(bytes)?;
+        if legacy.hash != ScriptLanguage::HASH {
+            return Err(ModelsStorageError::ModelsHash(legacy.hash));
+        }
+
+        Ok(BinStorage::from(legacy).to_bytes()?)
+    }
+
+    /// Checks the header, and the checksum of the archive.
+    /// Models binary without the header has no checksum, so only it's layout is checked.
+    fn verify_bytes(bytes: &[u8]) -> Result<(), ModelsStorageError> {
+        if let Err(e) = Self::split_header(bytes) {
+            return match e {
+                ModelsStorageError::FormatVersion { found: 0, .. }
+                    if Self::access_legacy(bytes).is_some() =>
+                {
+                    Ok(())
+                }
+                e => Err(e),
+            };
+        }
+
+        if bytes.get(CHECKSUM_RANGE) != Some(&checksum(bytes)[..]) {
+            return Err(ModelsStorageError::Checksum);
+        }
+
+        Ok(())
+    }
+
+    /// Re-checks the checksum of the models binary,
+    /// rejects truncated or tampered models.
+    ///
+    /// Not done on load, because it reads the whole binary.
+    #[inline]
+    pub fn verify(&self) -> Result<(), ModelsStorageError> {
+        Self::verify_bytes(self.bytes)
+    }
+
+    fn from_slice(bytes: &'m [u8], data: ModelsData) -> Result<Self, ModelsStorageError> {
+        let slice = match Self::split_header(bytes) {
+            Ok(slice) => slice,
+            Err(e @ ModelsStorageError::FormatVersion { found: 0, .. }) => {
+                if Self::access_legacy(bytes).is_none() {
+                    return Err(e);
+                }
+                return Self::from_vec(Self::convert_legacy(bytes)?);
+            }
+            Err(e) => return Err(e),
+        };
         let fs = rkyv::access::<ArchivedBinStorage, rkyv::rancor::Error>(slice)?;
 
         if fs.hash != ScriptLanguage::HASH {
             return Err(ModelsStorageError::ModelsHash(fs.hash.to_native()));
         }
 
+        let table_len = match &fs.quantized {
+            ArchivedQuantized::None => None,
+            ArchivedQuantized::U16(quantized) => {
+                Some((ProbabilityEncoding::U16, quantized.table.len()))
+            }
+            ArchivedQuantized::U8(quantized) => {
+                Some((ProbabilityEncoding::U8, quantized.table.len()))
+            }
+        };
+        if let Some((encoding, len)) = table_len {
+            if len != encoding.buckets() {
+                return Err(ModelsStorageError::QuantizationTable(len));
+            }
+        }
+
         Ok(Self {
-            mmap,
+            data,
+            bytes,
+            bin_storage: fs,
             langs_ngram_min_probability: &fs.langs_ngram_min_probability,
             ngrams: &fs.ngrams,
             wordgrams: &fs.wordgrams,
             wordgram_min_probability: fs.wordgram_min_probability.to_native(),
+            langs_ngram_scores: &fs.langs_ngram_scores,
+            quantized: &fs.quantized,
+            calibration: &fs.calibration,
         })
     }
 
-    #[cfg(test)]
-    pub fn from_models(input: impl IntoIterator<Item = (ScriptLanguage, Model)>) -> Self {
-        let mut file_storage = crate::bin_storage::BinStorage::default();
+    #[inline]
+    pub fn encoding(&self) -> ProbabilityEncoding {
+        match self.quantized {
+            ArchivedQuantized::None => ProbabilityEncoding::F64,
+            ArchivedQuantized::U16(_) => ProbabilityEncoding::U16,
+            ArchivedQuantized::U8(_) => ProbabilityEncoding::U8,
+        }
+    }
+
+    /// Calls `f` with the ngram model of `ngram_size` (wordgrams for [`NgramSize::Word`]),
+    /// and the dequantization table if models are quantized
+    #[inline(always)]
+    pub(super) fn with_ngram_model<F: NgramModelFn>(
+        &self,
+        ngram_size: NgramSize,
+        f: F,
+    ) -> F::Output {
+        match (ngram_size, self.quantized) {
+            (NgramSize::Word, ArchivedQuantized::None) => f.call::<f64>(self.wordgrams, &[]),
+            (_, ArchivedQuantized::None) => {
+                f.call::<f64>(self.ngrams.get_safe_unchecked(ngram_size as usize), &[])
+            }
+            (NgramSize::Word, ArchivedQuantized::U16(quantized)) => {
+                f.call::<u16>(&quantized.wordgrams, &quantized.table)
+            }
+            (_, ArchivedQuantized::U16(quantized)) => f.call::<u16>(
+                quantized.ngrams.get_safe_unchecked(ngram_size as usize),
+                &quantized.table,
+            ),
+            (NgramSize::Word, ArchivedQuantized::U8(quantized)) => {
+                f.call::<u8>(&quantized.wordgrams, &quantized.table)
+            }
+            (_, ArchivedQuantized::U8(quantized)) => f.call::<u8>(
+                quantized.ngrams.get_safe_unchecked(ngram_size as usize),
+                &quantized.table,
+            ),
+        }
+    }
+
+    /// Calibration of relative probabilities, empty if not calibrated
+    #[inline]
+    pub fn calibration(&self) -> Calibration {
+        self.calibration.to_native()
+    }
+
+    #[inline]
+    pub fn metadata(&self) -> ModelsMetadata {
+        ModelsMetadata::new(&self.bin_storage.metadata, FORMAT_VERSION, self.encoding())
+    }
+
+    /// Deserializes models, so they can be modified.
+    /// Quantized probabilities are converted back into `f64`.
+    #[inline]
+    pub fn to_bin_storage(&self) -> Result<BinStorage, ModelsStorageError> {
+        let mut bin_storage =
+            rkyv::deserialize::<BinStorage, rkyv::rancor::Error>(self.bin_storage)?;
+        bin_storage.dequantize();
+
+        Ok(bin_storage)
+    }
+
+    /// Merges multiple models into one.
+    /// Each next storage replaces models of the languages it contains
+    /// (see [`BinStorage::overlay`]).
+    pub fn merge<'s>(
+        layers: impl IntoIterator<Item = &'s ModelsStorage<'s>>,
+    ) -> Result<Self, ModelsStorageError> {
+        let mut layers = layers.into_iter();
+        let mut bin_storage = match layers.next() {
+            Some(base) => base.to_bin_storage()?,
+            None => BinStorage::default(),
+        };
+        for layer in layers {
+            bin_storage.overlay(layer.to_bin_storage()?);
+        }
+
+        let bytes = bin_storage.to_bytes()?;
+
+        Self::from_vec(bytes)
+    }
+
+    /// Builds models from in-process `Model`s, without writing a models file
+    pub fn from_models(
+        input: impl IntoIterator<Item = (ScriptLanguage, Model)>,
+    ) -> Result<Self, ModelsStorageError> {
+        let mut bin_storage = BinStorage::default();
 
         for (l, m) in input {
-            file_storage.add(l, m);
+            bin_storage.add(l, m);
         }
-        file_storage.finalize();
+        bin_storage.finalize();
 
-        let bytes = file_storage.to_bytes().unwrap();
-        let mmap = mmap_from_bytes(&bytes).unwrap();
+        let bytes = bin_storage.to_bytes()?;
 
-        Self::from_mmap(mmap).unwrap()
+        Self::from_vec(bytes)
     }
 }
 
-#[cfg(test)]
-fn mmap_from_bytes(data: &[u8]) -> ::std::io::Result<Mmap> {
-    let mut mmap = memmap2::MmapMut::map_anon(data.len())?;
-    mmap.copy_from_slice(data);
-    mmap.make_read_only()
+fn verify_sha256(bytes: &[u8], expected: [u8; 32]) -> Result<(), ModelsStorageError> {
+    let found: [u8; 32] = Sha256::digest(bytes).into();
+    if found != expected {
+        return Err(ModelsStorageError::Sha256 { expected, found });
+    }
+
+    Ok(())
+}
+
+/// `LANGRAM_CACHE_DIR`, or `langram` in the user cache directory
+fn default_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("LANGRAM_CACHE_DIR") {
+        return Some(dir.into());
+    }
+
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library").join("Caches"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| home().map(|home| home.join(".cache")))
+    };
+
+    base.map(|base| base.join("langram"))
+}
+
+fn display_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[derive(Error, Debug)]
 pub enum ModelsStorageError {
+    #[error("Langram models not found, probed: {}", display_paths(.0))]
+    NotFound(Vec<PathBuf>),
     #[error("Current exe error")]
     CurrentExe(#[source] io::Error),
+    #[error("Models cache dir create error")]
+    CacheDirCreate(#[source] io::Error),
     #[error("Models part file create error")]
     ModelsPartFileCreate(#[source] io::Error),
     #[error("Models part file remove error")]
@@ -169,16 +839,469 @@ pub enum ModelsStorageError {
     ModelsPartFileRename(#[source] io::Error),
     #[error("Failed to stream decompressed model bytes into file")]
     StreamDecompressor(#[source] io::Error),
+    #[cfg(feature = "download")]
     #[error("Failed to download models")]
     Download(#[source] reqwest::Error),
     #[error("Failed to flush the buffer")]
     WriterIntoInner(#[source] io::IntoInnerError<BufWriter<File>>),
+    #[error("Failed to write converted models")]
+    ConvertedWrite(#[source] io::Error),
     #[error("Failed to sync file data to disk")]
     FileSync(#[source] io::Error),
     #[error("Mmap error")]
     Mmap(#[source] io::Error),
+    /// `found` is 0 for a models binary without a header
+    #[error("Langram models format version {found} is incompatible, expected {expected}, please update models!")]
+    FormatVersion { expected: u32, found: u32 },
     #[error("Rkyv access error")]
     RkyvAccess(#[from] rkyv::rancor::Error),
+    #[error("Langram models checksum mismatch, the file is truncated or corrupted")]
+    Checksum,
+    #[error(
+        "Langram models SHA-256 mismatch, expected {}, found {}",
+        display_hex(expected),
+        display_hex(found)
+    )]
+    Sha256 { expected: [u8; 32], found: [u8; 32] },
     #[error("Langram models hash {0:X} is incompatible, please recompile models!")]
     ModelsHash(u64),
+    #[error("Langram models quantization table length {0} is invalid")]
+    QuantizationTable(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BinStorage, LegacyBinStorage, ModelsStorage, ModelsStorageError, ModelsStorageOptions,
+        FORMAT_VERSION, HEADER_LEN, MAGIC,
+    };
+    use crate::{model::Model, NgramSize};
+    use ::std::{
+        env, fs,
+        path::{Path, PathBuf},
+    };
+    use ::std::{
+        io::Write,
+        process::{Command, Stdio},
+    };
+    use alphabet_detector::ScriptLanguage;
+    use rkyv::util::AlignedVec;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_open_not_found() {
+        let dir = env::temp_dir().join("langram_test_open_not_found");
+        let _ = fs::remove_dir_all(&dir);
+
+        let file_path = dir.join(ModelsStorage::FILE_NAME);
+        let err = ModelsStorage::open(&file_path).unwrap_err();
+        assert!(matches!(err, ModelsStorageError::NotFound(probed) if probed == [file_path]));
+
+        fs::create_dir(&dir).unwrap();
+        let cache_dir = dir.join("cache");
+        let err = ModelsStorageOptions::new()
+            .path(&dir)
+            .cache_dir(&cache_dir)
+            .open()
+            .unwrap_err();
+        let cache_dir_exists = cache_dir.exists();
+        fs::remove_dir_all(&dir).unwrap();
+        let ModelsStorageError::NotFound(probed) = err else {
+            panic!("expected NotFound, got {err:?}");
+        };
+        assert_eq!(
+            probed,
+            [
+                dir.join(ModelsStorage::FILE_NAME),
+                dir.join(concat_const::concat!(ModelsStorage::FILE_NAME, ".br"))
+            ]
+        );
+        assert!(!cache_dir_exists);
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let bytes = BinStorage::default().to_bytes().unwrap();
+        ModelsStorage::from_bytes(&bytes).unwrap();
+
+        // unaligned
+        let mut unaligned = AlignedVec::<16>::with_capacity(bytes.len() + 1);
+        unaligned.push(0);
+        unaligned.extend_from_slice(&bytes);
+        ModelsStorage::from_bytes(&unaligned[1..]).unwrap();
+
+        ModelsStorage::from_vec(bytes).unwrap();
+    }
+
+    #[test]
+    fn test_from_bytes_format_version() {
+        let bytes = BinStorage::default().to_bytes().unwrap();
+
+        let err = ModelsStorage::from_bytes(&bytes[HEADER_LEN..]).unwrap_err();
+        assert!(matches!(
+            err,
+            ModelsStorageError::FormatVersion {
+                expected: FORMAT_VERSION,
+                found: 0
+            }
+        ));
+
+        let mut bytes = bytes;
+        bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let err = ModelsStorage::from_bytes(&bytes).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Langram models format version {} is incompatible, expected {FORMAT_VERSION}, please update models!",
+                FORMAT_VERSION + 1
+            )
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_hash() {
+        let bin_storage = BinStorage {
+            hash: 0,
+            ..Default::default()
+        };
+        let bytes = bin_storage.to_bytes().unwrap();
+        let err = ModelsStorage::from_bytes(&bytes).unwrap_err();
+        assert!(matches!(err, ModelsStorageError::ModelsHash(0)));
+    }
+
+    /// Models, and their binary without the header (`v0.11` models release)
+    fn legacy_bytes() -> (BinStorage, AlignedVec) {
+        let mut bin_storage = BinStorage::default();
+        let mut model = Model::default();
+        model[NgramSize::Uni as usize] = [("a", 0.5), ("b", 0.5)]
+            .map(|(ngram, prob)| (ngram.to_owned(), f64::ln(prob)))
+            .into_iter()
+            .collect();
+        bin_storage.add(ScriptLanguage::English, model.clone());
+        model[NgramSize::Uni as usize].remove("b");
+        bin_storage.add(ScriptLanguage::German, model);
+        bin_storage.finalize();
+
+        let legacy = LegacyBinStorage {
+            langs_ngram_min_probability: bin_storage.langs_ngram_min_probability,
+            ngrams: bin_storage.ngrams.clone(),
+            wordgrams: bin_storage.wordgrams.clone(),
+            wordgram_min_probability: bin_storage.wordgram_min_probability,
+            hash: bin_storage.hash,
+        };
+        let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&legacy).unwrap();
+
+        (bin_storage, bytes)
+    }
+
+    #[test]
+    fn test_from_bytes_legacy() {
+        let (bin_storage, bytes) = legacy_bytes();
+        ModelsStorage::verify_bytes(&bytes).unwrap();
+
+        let models_storage = ModelsStorage::from_bytes(&bytes).unwrap();
+        models_storage.verify().unwrap();
+        let converted = models_storage.to_bin_storage().unwrap();
+        assert_eq!(
+            converted.langs_ngram_min_probability,
+            bin_storage.langs_ngram_min_probability
+        );
+        assert!((converted.min_probability_shift - bin_storage.min_probability_shift).abs() < 1e-9);
+        assert_eq!(converted.ngrams, bin_storage.ngrams);
+        assert_eq!(
+            converted.metadata().languages,
+            [ScriptLanguage::English, ScriptLanguage::German]
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut bytes = BinStorage::default().to_bytes().unwrap();
+        ModelsStorage::from_bytes(&bytes).unwrap().verify().unwrap();
+
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let err = ModelsStorage::verify_bytes(&bytes).unwrap_err();
+        assert!(matches!(err, ModelsStorageError::Checksum));
+
+        // truncated
+        let err = ModelsStorage::verify_bytes(&bytes[..HEADER_LEN]).unwrap_err();
+        assert!(matches!(err, ModelsStorageError::Checksum));
+    }
+
+    #[test]
+    fn test_open_sha256() {
+        let dir = env::temp_dir().join("langram_test_open_sha256");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+
+        let bytes = BinStorage::default().to_bytes().unwrap();
+        fs::write(dir.join(ModelsStorage::FILE_NAME), &bytes).unwrap();
+        let sha256: [u8; 32] = Sha256::digest(&bytes).into();
+
+        let ok = ModelsStorageOptions::new().path(&dir).sha256(sha256).open();
+        let err = ModelsStorageOptions::new()
+            .path(&dir)
+            .sha256([0; 32])
+            .open()
+            .unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        ok.unwrap();
+        assert!(matches!(
+            err,
+            ModelsStorageError::Sha256 { expected, found } if expected == [0; 32] && found == sha256
+        ));
+    }
+
+    /// Writes compressed models into `dir`, returns decompressed
+    fn write_br(dir: &Path, corpus: &str) -> AlignedVec {
+        let mut bin_storage = BinStorage::default();
+        bin_storage.set_corpus(corpus);
+        let bytes = bin_storage.to_bytes().unwrap();
+        let compressed_file =
+            fs::File::create(dir.join(concat_const::concat!(ModelsStorage::FILE_NAME, ".br")))
+                .unwrap();
+        let mut writer = brotli::CompressorWriter::new(compressed_file, 4096, 11, 22);
+        writer.write_all(&bytes).unwrap();
+
+        bytes
+    }
+
+    /// Decompressed models files in the cache `dir`
+    fn cached_files(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                let file_name = path.file_name().unwrap().to_string_lossy();
+                file_name.ends_with(".bin") && file_name != ModelsStorage::FILE_NAME
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_open_cache_dir() {
+        let dir = env::temp_dir().join("langram_test_open_cache_dir");
+        let _ = fs::remove_dir_all(&dir);
+        let models_dir = dir.join("models");
+        let cache_dir = dir.join("cache");
+        let other_models_dir = dir.join("other_models");
+        fs::create_dir_all(&models_dir).unwrap();
+        fs::create_dir_all(&other_models_dir).unwrap();
+        let bytes = write_br(&models_dir, "");
+        write_br(&other_models_dir, "other");
+
+        let options = ModelsStorageOptions::new()
+            .path(&models_dir)
+            .cache_dir(&cache_dir);
+        let decompressed = options.open().map(|m| m.metadata().corpus);
+        let cached = cached_files(&cache_dir);
+        let cached_bytes = fs::read(&cached[0]);
+        let near = models_dir.join(ModelsStorage::FILE_NAME).exists();
+        let reopened = options.open().map(|m| m.metadata().corpus);
+        // other models path doesn't share the cached file
+        let other = ModelsStorageOptions::new()
+            .path(&other_models_dir)
+            .cache_dir(&cache_dir)
+            .open()
+            .map(|m| m.metadata().corpus);
+        let other_cached = cached_files(&cache_dir);
+        // changed `.br` replaces the cached file
+        write_br(&models_dir, "changed");
+        let changed = options.open().map(|m| m.metadata().corpus);
+        let changed_cached = cached_files(&cache_dir);
+        // not reused without `.br`
+        fs::remove_dir_all(&models_dir).unwrap();
+        fs::create_dir(&models_dir).unwrap();
+        let removed = options.open().map(|_| ());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(decompressed.unwrap(), "");
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached_bytes.unwrap(), bytes.as_slice());
+        assert_eq!(reopened.unwrap(), "");
+        assert!(!near);
+        assert_eq!(other.unwrap(), "other");
+        assert_eq!(other_cached.len(), 2);
+        assert_eq!(changed.unwrap(), "changed");
+        assert_eq!(changed_cached.len(), 2);
+        assert!(!changed_cached.contains(&cached[0]));
+        assert!(matches!(removed, Err(ModelsStorageError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_open_legacy() {
+        let dir = env::temp_dir().join("langram_test_open_legacy");
+        let _ = fs::remove_dir_all(&dir);
+        let models_dir = dir.join("models");
+        let cache_dir = dir.join("cache");
+        fs::create_dir_all(&models_dir).unwrap();
+        let (bin_storage, bytes) = legacy_bytes();
+        fs::write(models_dir.join(ModelsStorage::FILE_NAME), &bytes).unwrap();
+        let sha256: [u8; 32] = Sha256::digest(&bytes).into();
+
+        let options = ModelsStorageOptions::new()
+            .path(&models_dir)
+            .cache_dir(&cache_dir)
+            .sha256(sha256);
+        let converted = options.open().map(|m| m.metadata().languages);
+        let cached = cached_files(&cache_dir);
+        let cached_bytes = fs::read(&cached[0]).unwrap();
+        let reopened = options.open().map(|m| m.to_bin_storage().unwrap().ngrams);
+        let err = ModelsStorageOptions::new()
+            .path(&models_dir)
+            .cache_dir(&cache_dir)
+            .sha256([0; 32])
+            .open()
+            .unwrap_err();
+        let cached_after_err = cached_files(&cache_dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            converted.unwrap(),
+            [ScriptLanguage::English, ScriptLanguage::German]
+        );
+        assert_eq!(cached.len(), 1);
+        // converted once into the current format
+        assert!(cached_bytes.starts_with(&MAGIC));
+        ModelsStorage::verify_bytes(&cached_bytes).unwrap();
+        assert_eq!(reopened.unwrap(), bin_storage.ngrams);
+        // sha256 is of the source file
+        assert!(matches!(err, ModelsStorageError::Sha256 { found, .. } if found == sha256));
+        assert_eq!(cached_after_err, cached);
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_download() {
+        let dir = env::temp_dir().join("langram_test_download");
+        let _ = fs::remove_dir_all(&dir);
+        let (bin_storage, bytes) = legacy_bytes();
+        let sha256: [u8; 32] = Sha256::digest(&bytes).into();
+        let mut compressed = Vec::new();
+        brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22)
+            .write_all(&bytes)
+            .unwrap();
+        let response = || Ok(compressed.as_slice());
+
+        let options = ModelsStorageOptions::new();
+        let mismatch = options.download_into(&dir, [0; 32], response).map(|_| ());
+        let mismatch_cached = cached_files(&dir);
+        let downloaded = options.download_into(&dir, sha256, response).map(|_| ());
+        let cached = cached_files(&dir);
+        let cached_bytes = fs::read(&cached[0]).unwrap();
+        // not downloaded again
+        let reused = options.download_into(&dir, sha256, || -> Result<&[u8], _> {
+            panic!("downloaded again")
+        });
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(
+            mismatch,
+            Err(ModelsStorageError::Sha256 { expected, found }) if expected == [0; 32] && found == sha256
+        ));
+        assert!(mismatch_cached.is_empty());
+        downloaded.unwrap();
+        assert_eq!(cached.len(), 1);
+        let models_storage = ModelsStorage::from_bytes(&cached_bytes).unwrap();
+        models_storage.verify().unwrap();
+        assert_eq!(
+            models_storage.to_bin_storage().unwrap().ngrams,
+            bin_storage.ngrams
+        );
+        reused.unwrap();
+    }
+
+    #[test]
+    fn test_open_br_concurrent() {
+        let dir = env::temp_dir().join("langram_test_open_br_concurrent");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+
+        let bytes = write_br(&dir, "");
+
+        // Loader processes run `test_open_br_concurrent_loader`
+        let loaders: Vec<_> = (0..8)
+            .map(|_| {
+                Command::new(env::current_exe().unwrap())
+                    .args(["--exact", "--ignored", "--quiet"])
+                    .arg("detector::storage::tests::test_open_br_concurrent_loader")
+                    .env("LANGRAM_MODELS_PATH", &dir)
+                    .env("LANGRAM_CACHE_DIR", &dir)
+                    .stdout(Stdio::null())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+        let results: Vec<_> = loaders
+            .into_iter()
+            .map(|mut loader| loader.wait().unwrap())
+            .collect();
+        let cached = cached_files(&dir);
+        let file = fs::read(&cached[0]);
+        let part_files = fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".part")
+            })
+            .count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(results.iter().all(|status| status.success()));
+        assert_eq!(cached.len(), 1);
+        assert_eq!(file.unwrap(), bytes.as_slice());
+        assert_eq!(part_files, 0);
+    }
+
+    #[test]
+    fn test_open_env_not_found() {
+        let dir = env::temp_dir().join("langram_test_open_env_not_found");
+        let _ = fs::remove_dir_all(&dir);
+
+        let status = Command::new(env::current_exe().unwrap())
+            .args(["--exact", "--ignored", "--quiet"])
+            .arg("detector::storage::tests::test_open_env_not_found_loader")
+            .env("LANGRAM_MODELS_PATH", dir.join(ModelsStorage::FILE_NAME))
+            .env("LANGRAM_CACHE_DIR", dir.join("cache"))
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        let dir_exists = dir.exists();
+
+        assert!(status.success());
+        assert!(!dir_exists);
+    }
+
+    /// Spawned by `test_open_env_not_found`
+    #[test]
+    #[ignore]
+    fn test_open_env_not_found_loader() {
+        let Some(path) = env::var_os("LANGRAM_MODELS_PATH") else {
+            return;
+        };
+        let path = Path::new(&path);
+
+        let err = ModelsStorageOptions::new().open().unwrap_err();
+        let ModelsStorageError::NotFound(probed) = err else {
+            panic!("expected NotFound, got {err:?}");
+        };
+        assert_eq!(probed[0], path.join(ModelsStorage::FILE_NAME));
+    }
+
+    /// Spawned by `test_open_br_concurrent`
+    #[test]
+    #[ignore]
+    fn test_open_br_concurrent_loader() {
+        if env::var_os("LANGRAM_MODELS_PATH").is_none() {
+            return;
+        }
+
+        ModelsStorage::new().unwrap().verify().unwrap();
+    }
 }
@@ -0,0 +1,234 @@
+use super::{order_by_probability_and_lang, top_one_or_none, Detector, ProbabilitiesAdder};
+use crate::ngram_size::{NgramSize, NgramSizes, NgramSizesTrait};
+use ::std::{io, mem, str};
+use alphabet_detector::{
+    fulltext_filter_with_margin, slang_arr_default, ScriptLanguage, ScriptLanguageArr,
+};
+use debug_unsafe::{option::OptionUnwrapper, slice::SliceGetter};
+use rustc_hash::FxHashSet;
+use strum::EnumCount;
+
+/// Pending text without whitespace is processed anyway after this length in bytes,
+/// for scripts which don't separate words
+const MAX_PENDING_LEN: usize = 1 << 16;
+const READ_BUF_LEN: usize = 1 << 13;
+
+/// Incremental detection of a text fed in chunks, created by [`Detector::stream`].
+///
+/// Chunks are split at whitespace, so words split across chunk boundaries are processed whole.
+/// Unlike [`Detector::probabilities`], ngrams are deduplicated only within a processed chunk,
+/// and a language first identified by `alphabet_detector` in a later chunk
+/// doesn't get minimal probabilities of the previous chunks ngrams.
+#[derive(Clone, Debug)]
+pub struct DetectorStream<'d, 'm> {
+    detector: &'d Detector<'m>,
+    /// Union of short text and long text ngram sizes
+    ngram_sizes: NgramSizes,
+    /// Text after the last whitespace, can be a start of a word split by a chunk boundary
+    pending: String,
+    /// Languages identified by `alphabet_detector` so far
+    languages: FxHashSet<ScriptLanguage>,
+    /// Sums of logarithmic probabilities, and counts of found ngrams, indexed by `NgramSize`
+    probabilities: Vec<ScriptLanguageArr<(f64, usize)>>,
+    characters_count: usize,
+}
+
+impl<'d, 'm> DetectorStream<'d, 'm> {
+    fn new(detector: &'d Detector<'m>) -> Self {
+        let mut ngram_sizes = detector.short_text_ngram_sizes.clone();
+        ngram_sizes.merge(detector.long_text_ngram_sizes.iter().copied());
+
+        Self {
+            detector,
+            ngram_sizes,
+            pending: String::new(),
+            languages: Default::default(),
+            probabilities: vec![slang_arr_default(); NgramSize::COUNT],
+            characters_count: 0,
+        }
+    }
+
+    fn process(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let (words, langs, _) = fulltext_filter_with_margin::<Vec<char>, 95>(text.char_indices());
+        self.languages.extend(
+            langs
+                .filter(|(l, _)| self.detector.languages.contains(l))
+                .map(|(l, _)| l),
+        );
+        if words.is_empty() || self.languages.is_empty() {
+            return;
+        }
+
+        self.characters_count += words.iter().map(|wd| wd.buf.len()).sum::<usize>();
+
+        let models_storage = self.detector.models_storage;
+        for &ngram_size in self.ngram_sizes.iter() {
+            let output = self
+                .probabilities
+                .get_safe_unchecked_mut(ngram_size as usize);
+            if ngram_size == NgramSize::Word {
+                Detector::probabilities_languages_wordgrams(
+                    models_storage,
+                    words.iter().map(|wd| wd.buf.iter().collect::<String>()),
+                    &self.languages,
+                    output,
+                );
+            } else {
+                Detector::probabilities_ngrams(
+                    models_storage,
+                    words.iter().map(|wd| wd.buf.as_ref()),
+                    &self.languages,
+                    ngram_size,
+                    output,
+                );
+            }
+        }
+    }
+
+    /// Feeds the next chunk of the text.
+    /// Text after the last whitespace is kept pending until the next chunk or [`finish`](Self::finish).
+    pub fn push(&mut self, chunk: &str) {
+        self.pending.push_str(chunk);
+
+        let split = match self.pending.rfind(char::is_whitespace) {
+            Some(i) => i,
+            None if self.pending.len() > MAX_PENDING_LEN => self.pending.len(),
+            None => return,
+        };
+
+        let pending = mem::take(&mut self.pending);
+        self.process(&pending[..split]);
+        self.pending = pending;
+        self.pending.drain(..split);
+    }
+
+    /// Feeds all text from `reader` (can be a [`BufRead`](io::BufRead) too),
+    /// stops early if `minimum_distance` is provided and
+    /// [`detect_top_one_or_none`](Self::detect_top_one_or_none) returns a language.
+    ///
+    /// Returns [`io::ErrorKind::InvalidData`] if the text is not valid UTF-8.
+    pub fn read_from(
+        &mut self,
+        mut reader: impl io::Read,
+        minimum_distance: Option<f64>,
+    ) -> io::Result<()> {
+        let mut buf = [0u8; READ_BUF_LEN];
+        // length of an incomplete char, split by the previous read
+        let mut len = 0;
+        loop {
+            let n = match reader.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            len += n;
+
+            let valid = match str::from_utf8(&buf[..len]) {
+                Ok(text) => text.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            };
+            self.push(unsafe { str::from_utf8_unchecked(&buf[..valid]) });
+            buf.copy_within(valid..len, 0);
+            len -= valid;
+
+            if minimum_distance.is_some_and(|d| self.detect_top_one_or_none(d).is_some()) {
+                return Ok(());
+            }
+        }
+
+        if len != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Characters count of the processed words
+    #[inline]
+    pub fn characters_count(&self) -> usize {
+        self.characters_count
+    }
+
+    /// Returns probabilities of the processed text, without the pending text,
+    /// same as [`Detector::probabilities`].
+    pub fn current(&self) -> Vec<(ScriptLanguage, f64)> {
+        if self.languages.len() == 1 {
+            let lang = *self.languages.iter().next().unwrap_safe_unchecked();
+            return vec![(lang, 0.0)];
+        }
+
+        let ngram_sizes = if self.characters_count >= self.detector.long_text_minlen {
+            &self.detector.long_text_ngram_sizes
+        } else {
+            &self.detector.short_text_ngram_sizes
+        };
+
+        let mut res: Vec<_> = self
+            .languages
+            .iter()
+            .map(|&language| {
+                let mut probability = (0.0, 0);
+                for &ngram_size in ngram_sizes.iter() {
+                    probability.add(
+                        *self
+                            .probabilities
+                            .get_safe_unchecked(ngram_size as usize)
+                            .get_safe_unchecked(language as usize),
+                    );
+                }
+
+                (
+                    language,
+                    self.detector.language_mean(language, probability, None),
+                )
+            })
+            .collect();
+        res.sort_unstable_by(order_by_probability_and_lang);
+
+        res
+    }
+
+    /// Detects a top one language of the processed text,
+    /// same as [`Detector::detect_top_one_or_none`].
+    /// Can be used to stop feeding the text, when the leader's margin is decisive.
+    pub fn detect_top_one_or_none(&self, minimum_distance: f64) -> Option<ScriptLanguage> {
+        top_one_or_none(self.current(), minimum_distance)
+    }
+
+    /// Processes the pending text, and returns probabilities of the whole text
+    pub fn finish(mut self) -> Vec<(ScriptLanguage, f64)> {
+        let pending = mem::take(&mut self.pending);
+        self.process(&pending);
+
+        self.current()
+    }
+}
+
+impl<'m> Detector<'m> {
+    /// Creates [`DetectorStream`] to detect a text fed in chunks
+    #[inline]
+    pub fn stream(&self) -> DetectorStream<'_, 'm> {
+        DetectorStream::new(self)
+    }
+
+    /// Returns probabilities for all text from `reader` (can be a [`BufRead`](io::BufRead) too),
+    /// see [`DetectorStream`]
+    pub fn probabilities_reader(
+        &self,
+        reader: impl io::Read,
+    ) -> io::Result<Vec<(ScriptLanguage, f64)>> {
+        let mut stream = self.stream();
+        stream.read_from(reader, None)?;
+
+        Ok(stream.finish())
+    }
+}
@@ -0,0 +1,165 @@
+use super::{
+    storage::{NgramModel, NgramModelFn, Probability},
+    DetectionResult, Detector,
+};
+use crate::ngram_size::NgramSize;
+use alphabet_detector::{ScriptLanguage, Word};
+use debug_unsafe::slice::SliceGetter;
+use rkyv::{tuple::ArchivedTuple2, Archived};
+
+/// Result of [`Detector::detect_or_unknown`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DetectedLanguage {
+    Known(ScriptLanguage),
+    /// Text is not similar to any model of the selected languages:
+    /// random strings, base64, code identifiers, or a language without a model
+    Unknown,
+}
+
+impl DetectedLanguage {
+    #[inline]
+    pub fn known(self) -> Option<ScriptLanguage> {
+        match self {
+            Self::Known(language) => Some(language),
+            Self::Unknown => None,
+        }
+    }
+}
+
+/// Probability of `ngram` in the `language` model
+struct LanguageProbability<'a> {
+    ngram: &'a str,
+    language: ScriptLanguage,
+}
+
+impl NgramModelFn for LanguageProbability<'_> {
+    type Output = Option<f64>;
+
+    #[inline]
+    fn call<P: Probability>(
+        self,
+        ngram_model: &NgramModel<P>,
+        table: &[Archived<f64>],
+    ) -> Option<f64> {
+        ngram_model
+            .get(self.ngram)?
+            .iter()
+            .find(|ArchivedTuple2(l, _)| l.to_native() as usize == self.language as usize)
+            .map(|ArchivedTuple2(_, prob)| P::dequantize(prob, table))
+    }
+}
+
+impl Detector<'_> {
+    /// Returns the mean logarithmic probability of all ngrams of `words` in the `language` model
+    /// (ngrams not found get the model's minimal probability),
+    /// and the expected mean and standard error of it for a text of the `language`
+    /// (weighted by ngrams count of each size).
+    ///
+    /// Word ngrams are used only if there are no other ngram sizes,
+    /// because unknown words are common.
+    fn language_score(
+        &self,
+        words: &[Word<Vec<char>>],
+        language: ScriptLanguage,
+        ngram_sizes: &[NgramSize],
+    ) -> Option<(f64, f64, f64)> {
+        let scores = self
+            .models_storage
+            .langs_ngram_scores
+            .get_safe_unchecked(language as usize);
+        let char_ngram_sizes = ngram_sizes.iter().any(|&s| s != NgramSize::Word);
+
+        let (mut sum, mut cnt) = (0.0, 0usize);
+        let (mut mean, mut variance, mut found) = (0.0, 0.0, false);
+        for &ngram_size in ngram_sizes {
+            if char_ngram_sizes && ngram_size == NgramSize::Word {
+                continue;
+            }
+            let score = scores.get_safe_unchecked(ngram_size as usize);
+            let min = score.min.to_native();
+            if min == f64::NEG_INFINITY {
+                continue;
+            }
+            found = true;
+
+            let mut size_cnt = 0;
+            let mut add = |ngram: &str| {
+                sum += self
+                    .models_storage
+                    .with_ngram_model(ngram_size, LanguageProbability { ngram, language })
+                    .unwrap_or(min);
+                size_cnt += 1;
+            };
+            if ngram_size == NgramSize::Word {
+                for word in words {
+                    add(&word.buf.iter().collect::<String>());
+                }
+            } else {
+                let mut ngram = String::new();
+                for window in words
+                    .iter()
+                    .flat_map(|wd| wd.buf.windows(ngram_size as usize + 1))
+                {
+                    ngram.clear();
+                    ngram.extend(window);
+                    add(&ngram);
+                }
+            }
+
+            cnt += size_cnt;
+            mean += size_cnt as f64 * score.mean.to_native();
+            variance += size_cnt as f64 * score.deviation.to_native().powi(2);
+        }
+
+        if !found {
+            return None;
+        }
+        if cnt == 0 {
+            return Some((0.0, 0.0, 0.0));
+        }
+
+        // the deviation of a mean of `cnt` ngrams
+        let cnt = cnt as f64;
+        Some((sum / cnt, mean / cnt, variance.sqrt() / cnt))
+    }
+
+    /// Detects a top one language of the provided text,
+    /// or rejects it as [`DetectedLanguage::Unknown`].
+    ///
+    /// The text is rejected, if it's mean ngram logarithmic probability in the top language model
+    /// is lower than typical for that language by more than `max_deviation`
+    /// standard errors (standard deviations of the mean of the text's ngrams count),
+    /// so longer texts are rejected by a smaller difference.
+    /// Also rejected, if the top language has no model.
+    pub fn detect_or_unknown(&self, text: &str, max_deviation: f64) -> DetectedLanguage {
+        debug_assert!(max_deviation >= 0.0, "Max deviation must be >= 0.0");
+
+        let DetectionResult {
+            probabilities,
+            words,
+            ngram_sizes,
+            single_language,
+            ..
+        } = self.detect(text);
+        let Some(&(language, _)) = probabilities.first() else {
+            return DetectedLanguage::Unknown;
+        };
+
+        let ngram_sizes = if single_language {
+            &self.short_text_ngram_sizes
+        } else {
+            &ngram_sizes
+        };
+        let Some((score, mean, standard_error)) =
+            self.language_score(&words, language, ngram_sizes)
+        else {
+            return DetectedLanguage::Unknown;
+        };
+
+        if score < mean - max_deviation * standard_error {
+            return DetectedLanguage::Unknown;
+        }
+
+        DetectedLanguage::Known(language)
+    }
+}
@@ -9,7 +9,17 @@
 //!
 //! # Setup
 //!
-//! To use this library, you need a binary models file, which must be placed near the executable, or set `LANGRAM_MODELS_PATH`.
+//! To use this library, you need a binary models file, which must be placed near the executable, or set `LANGRAM_MODELS_PATH`,
+//! or opened with an explicit path using [`ModelsStorage::open`](struct.ModelsStorage.html#method.open).
+//! Compressed `langram_models.bin.br` is decompressed automatically into the user cache directory,
+//! or `LANGRAM_CACHE_DIR`, or [`ModelsStorageOptions::cache_dir`](struct.ModelsStorageOptions.html#method.cache_dir).
+//! Models can also be loaded from memory (`include_bytes!`, WASM) using
+//! [`ModelsStorage::from_bytes`](struct.ModelsStorage.html#method.from_bytes).
+//!
+//! The network is never used, unless the `download` feature is enabled,
+//! then [`ModelsStorage::new`](struct.ModelsStorage.html#method.new) downloads the models if they are not found.
+//! Decompressed or downloaded models are verified before they are saved,
+//! downloaded models also with the SHA-256 of the release, an expected SHA-256 can be pinned with [`ModelsStorageOptions::sha256`](struct.ModelsStorageOptions.html#method.sha256).
 //!
 //! It can be:
 //!
@@ -39,6 +49,10 @@
 //!     .collect();
 //! ```
 //! `detector` also has [other methods](struct.Detector.html#implementations)
+//!
+//! With the `rayon` feature enabled, [`Detector::par_detect_top_one`](struct.Detector.html#method.par_detect_top_one)
+//! and [`Detector::par_probabilities`](struct.Detector.html#method.par_probabilities)
+//! detect texts in parallel, reusing buffers in each thread.
 
 #[cfg(test)]
 macro_rules! ahashmap {
@@ -78,14 +92,20 @@ macro_rules! ahashset {
 }
 
 pub use alphabet_detector::{
-    EnumCount, IntoEnumIterator, Language, Script, ScriptLanguage, UcdScript,
+    EnumCount, IntoEnumIterator, Language, Script, ScriptLanguage, UcdScript, Word,
 };
 
 pub mod bin_storage;
+pub mod calibration;
 mod detector;
 pub mod model;
 pub mod ngram_size;
 mod ngrams;
+pub mod priors;
 
-pub use detector::{Detector, DetectorBuilder, ModelsStorage, ModelsStorageError};
+pub use detector::{
+    Context, DetectedLanguage, DetectionResult, Detector, DetectorBuilder, DetectorScratch,
+    DetectorStream, Explanation, LanguageFilter, LanguageGroup, ModelsStorage, ModelsStorageError,
+    ModelsStorageOptions, NgramExplanation, NgramProbability, NgramSizeExplanation, Segment,
+};
 pub use ngram_size::NgramSize;
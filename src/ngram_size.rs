@@ -1,10 +1,13 @@
 use arrayvec::ArrayVec;
 use strum::EnumCount;
-use strum_macros::{EnumCount, EnumIter};
+use strum_macros::{EnumCount, EnumIter, EnumString};
 
 pub(crate) const NGRAM_MAX_LEN: usize = 5;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, EnumCount, EnumIter)]
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, EnumCount, EnumIter, EnumString,
+)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
 #[repr(usize)]
 pub enum NgramSize {
     Uni = 0,
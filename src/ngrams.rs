@@ -1,16 +1,17 @@
-use crate::NgramSize;
+use crate::{ngram_size::NGRAM_MAX_LEN, NgramSize};
+use ::std::borrow::BorrowMut;
 use arraystring::{typenum::U20, ArrayString};
-use debug_unsafe::arraystring::ArrayStringFrom;
+use debug_unsafe::{arraystring::ArrayStringFrom, slice::SliceGetter};
 use rustc_hash::FxHashSet;
 
 pub(crate) type NgramString = ArrayString<U20>;
 
-pub(crate) struct NgramIterator<'w, I>
+pub(crate) struct NgramIterator<'w, I, S = FxHashSet<&'w [char]>>
 where
     I: Iterator<Item = &'w [char]>,
 {
     ngrams: I,
-    seen: FxHashSet<&'w [char]>,
+    seen: S,
 }
 
 pub(crate) fn ngram_iterator<'w>(
@@ -25,16 +26,52 @@ pub(crate) fn ngram_iterator<'w>(
     }
 }
 
-impl<'w, I> Iterator for NgramIterator<'w, I>
+/// Skips ngrams already `seen`, so a text can be iterated in parts
+pub(crate) fn ngram_iterator_with_seen<'w, 's>(
+    words_iter: impl Iterator<Item = &'w [char]>,
+    ngram_size: NgramSize,
+    seen: &'s mut FxHashSet<&'w [char]>,
+) -> NgramIterator<'w, impl Iterator<Item = &'w [char]>, &'s mut FxHashSet<&'w [char]>> {
+    let ngrams = words_iter.flat_map(move |w| w.windows(ngram_size as usize + 1));
+
+    NgramIterator { ngrams, seen }
+}
+
+/// Ngram chars padded with `'\0'`, an owned key of a seen ngram
+pub(crate) type NgramKey = [char; NGRAM_MAX_LEN];
+
+/// Same as [`ngram_iterator`], but `seen` is not bound to the words lifetime,
+/// so it can be reused for multiple texts
+pub(crate) fn ngram_iterator_with_keys<'w, 's, I>(
+    words_iter: I,
+    ngram_size: NgramSize,
+    seen: &'s mut FxHashSet<NgramKey>,
+) -> impl Iterator<Item = NgramString> + use<'w, 's, I>
+where
+    I: Iterator<Item = &'w [char]>,
+{
+    words_iter
+        .flat_map(move |w| w.windows(ngram_size as usize + 1))
+        .filter_map(move |ngram| {
+            let mut key = ['\0'; NGRAM_MAX_LEN];
+            key.get_safe_unchecked_mut(..ngram.len())
+                .copy_from_slice(ngram);
+            seen.insert(key)
+                .then(|| NgramString::from_chars_safe_unchecked(ngram.iter().copied()))
+        })
+}
+
+impl<'w, I, S> Iterator for NgramIterator<'w, I, S>
 where
     I: Iterator<Item = &'w [char]>,
+    S: BorrowMut<FxHashSet<&'w [char]>>,
 {
     type Item = NgramString;
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             let ngram = self.ngrams.next()?;
-            if self.seen.insert(ngram) {
+            if self.seen.borrow_mut().insert(ngram) {
                 return Some(NgramString::from_chars_safe_unchecked(
                     ngram.iter().copied(),
                 ));
@@ -0,0 +1,241 @@
+use alphabet_detector::{EnumCount, IntoEnumIterator, ScriptLanguage};
+use debug_unsafe::slice::SliceGetter;
+use rkyv::util::AlignedVec;
+use thiserror::Error;
+
+/// Pseudo count of each language, so unobserved languages have a finite prior
+const PSEUDO_COUNT: f64 = 1.0;
+/// Weights are rescaled, when the observation weight exceeds it
+const MAX_WEIGHT: f64 = 1e100;
+
+/// Languages priors learned from the observed traffic,
+/// applied to a detector with [`DetectorBuilder::priors`](crate::DetectorBuilder::priors),
+/// or by setting [`Detector::priors`](crate::Detector::priors).
+///
+/// Each observation decays weights of the previous observations,
+/// so stale traffic matters less.
+///
+/// Can be persisted with [`to_bytes`](Self::to_bytes) and restored with [`from_bytes`](Self::from_bytes).
+#[derive(Clone, Debug, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+pub struct AdaptivePriors {
+    /// Weighted counts of observations, indexed by language
+    counts: Vec<f64>,
+    /// Sum of `counts`
+    total: f64,
+    /// Weight of the next observation, grows instead of decaying previous observations
+    weight: f64,
+    /// Weight multiplier of previous observations, applied on each observation
+    decay: f64,
+    /// `ScriptLanguage::HASH`, as `counts` are indexed by language
+    hash: u64,
+}
+
+#[derive(Error, Debug)]
+pub enum PriorsError {
+    #[error("Rkyv error")]
+    Rkyv(#[from] rkyv::rancor::Error),
+    #[error("Priors languages hash {0:X} is incompatible, priors must be learned again")]
+    Hash(u64),
+}
+
+impl AdaptivePriors {
+    /// `half_life` is a count of observations, after which an observation weight halves.
+    /// Infinity disables the decay.
+    ///
+    /// Panics if `half_life` is not positive (or NaN).
+    pub fn new(half_life: f64) -> Self {
+        assert!(half_life > 0.0, "Half life must be > 0.0");
+
+        Self {
+            counts: vec![0.0; ScriptLanguage::COUNT],
+            total: 0.0,
+            weight: 1.0,
+            decay: 0.5f64.powf(1.0 / half_life),
+            hash: ScriptLanguage::HASH,
+        }
+    }
+
+    /// Observes a detected language
+    pub fn observe(&mut self, language: ScriptLanguage) {
+        *self.counts.get_safe_unchecked_mut(language as usize) += self.weight;
+        self.total += self.weight;
+        self.weight /= self.decay;
+
+        if self.weight > MAX_WEIGHT {
+            let weight = self.weight;
+            self.counts.iter_mut().for_each(|c| *c /= weight);
+            self.total /= weight;
+            self.weight = 1.0;
+        }
+    }
+
+    /// Observes the top language of relative probabilities
+    /// (see [`Detector::probabilities_relative`](crate::Detector::probabilities_relative)),
+    /// only if it's confident: its probability is at least `min_probability`.
+    ///
+    /// Returns `true` if observed.
+    pub fn observe_confident(
+        &mut self,
+        probabilities_relative: &[(ScriptLanguage, f64)],
+        min_probability: f64,
+    ) -> bool {
+        match probabilities_relative.first() {
+            Some(&(language, probability)) if probability >= min_probability => {
+                self.observe(language);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Prior probability of the language, a number between 0.0 and 1.0
+    #[inline]
+    pub fn probability(&self, language: ScriptLanguage) -> f64 {
+        // the last observation has the weight 1.0
+        let scale = self.weight * self.decay;
+        (self.counts.get_safe_unchecked(language as usize) / scale + PSEUDO_COUNT)
+            / (self.total / scale + PSEUDO_COUNT * ScriptLanguage::COUNT as f64)
+    }
+
+    /// Logarithmic priors of all languages, for [`DetectorBuilder::priors`](crate::DetectorBuilder::priors).
+    ///
+    /// Relative to the uniform distribution, so the prior of a language is 0.0
+    /// if it's observed as often as on average, and all priors are 0.0 if nothing is observed.
+    pub fn priors(&self) -> impl Iterator<Item = (ScriptLanguage, f64)> + '_ {
+        ScriptLanguage::iter().map(|language| {
+            (
+                language,
+                (self.probability(language) * ScriptLanguage::COUNT as f64).ln(),
+            )
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<AlignedVec, rkyv::rancor::Error> {
+        rkyv::to_bytes::<rkyv::rancor::Error>(self)
+    }
+
+    /// Restores priors saved with [`to_bytes`](Self::to_bytes).
+    ///
+    /// Priors saved with different languages (`alphabet_detector` version) are rejected.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PriorsError> {
+        let mut aligned = AlignedVec::<16>::with_capacity(bytes.len());
+        aligned.extend_from_slice(bytes);
+
+        let priors = rkyv::from_bytes::<Self, rkyv::rancor::Error>(&aligned)?;
+        if priors.hash != ScriptLanguage::HASH || priors.counts.len() != ScriptLanguage::COUNT {
+            return Err(PriorsError::Hash(priors.hash));
+        }
+
+        Ok(priors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdaptivePriors, PriorsError};
+    use alphabet_detector::{EnumCount, ScriptLanguage};
+
+    fn prior(priors: &AdaptivePriors, language: ScriptLanguage) -> f64 {
+        priors.priors().find(|(l, _)| *l == language).unwrap().1
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut priors = AdaptivePriors::new(f64::INFINITY);
+        assert!(priors.priors().all(|(_, p)| p == 0.0));
+
+        for _ in 0..100 {
+            priors.observe(ScriptLanguage::Portuguese);
+        }
+        assert!(prior(&priors, ScriptLanguage::Portuguese) > 0.0);
+        assert!(prior(&priors, ScriptLanguage::Spanish) < 0.0);
+        assert_eq!(
+            prior(&priors, ScriptLanguage::Spanish),
+            prior(&priors, ScriptLanguage::English)
+        );
+
+        let sum: f64 = priors.priors().map(|(l, _)| priors.probability(l)).sum();
+        assert!((sum - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_decay() {
+        let mut priors = AdaptivePriors::new(10.0);
+        for _ in 0..100 {
+            priors.observe(ScriptLanguage::Portuguese);
+        }
+        for _ in 0..100 {
+            priors.observe(ScriptLanguage::Spanish);
+        }
+        assert!(
+            prior(&priors, ScriptLanguage::Spanish) > prior(&priors, ScriptLanguage::Portuguese)
+        );
+
+        // without decay both are equal
+        let mut priors = AdaptivePriors::new(f64::INFINITY);
+        for _ in 0..100 {
+            priors.observe(ScriptLanguage::Portuguese);
+        }
+        for _ in 0..100 {
+            priors.observe(ScriptLanguage::Spanish);
+        }
+        assert_eq!(
+            prior(&priors, ScriptLanguage::Spanish),
+            prior(&priors, ScriptLanguage::Portuguese)
+        );
+    }
+
+    #[test]
+    fn test_rescale() {
+        let mut priors = AdaptivePriors::new(1.0);
+        for _ in 0..1000 {
+            priors.observe(ScriptLanguage::Spanish);
+        }
+        assert!(priors.weight <= super::MAX_WEIGHT);
+        // sum of weights is 1.0 + 0.5 + 0.25 + ...
+        let expected = 3.0 / (2.0 + ScriptLanguage::COUNT as f64);
+        assert!((priors.probability(ScriptLanguage::Spanish) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_observe_confident() {
+        let mut priors = AdaptivePriors::new(100.0);
+        let probabilities = [
+            (ScriptLanguage::Portuguese, 0.6),
+            (ScriptLanguage::Spanish, 0.4),
+        ];
+        assert!(!priors.observe_confident(&probabilities, 0.9));
+        assert!(!priors.observe_confident(&[], 0.9));
+        assert_eq!(priors, AdaptivePriors::new(100.0));
+
+        assert!(priors.observe_confident(&probabilities, 0.5));
+        assert!(prior(&priors, ScriptLanguage::Portuguese) > 0.0);
+    }
+
+    #[test]
+    fn test_bytes() {
+        let mut priors = AdaptivePriors::new(100.0);
+        priors.observe(ScriptLanguage::Portuguese);
+        priors.observe(ScriptLanguage::Spanish);
+
+        let bytes = priors.to_bytes().unwrap();
+        assert_eq!(AdaptivePriors::from_bytes(&bytes).unwrap(), priors);
+        assert!(matches!(
+            AdaptivePriors::from_bytes(&bytes[1..]),
+            Err(PriorsError::Rkyv(_))
+        ));
+
+        priors.hash = 0;
+        let bytes = priors.to_bytes().unwrap();
+        assert!(matches!(
+            AdaptivePriors::from_bytes(&bytes),
+            Err(PriorsError::Hash(0))
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Half life must be > 0.0")]
+    fn test_zero_half_life() {
+        AdaptivePriors::new(0.0);
+    }
+}
@@ -212,6 +212,37 @@ fn test_detect_top_one_raw_is_deterministic(text: &str, languages: AHashSet<Scri
     );
 }
 
+#[test]
+fn test_detect_segments() {
+    let text = "ام وی با نیکی میناج تیزر داشت؟؟؟؟؟؟ i vote for bts ( _ ) as the _ via ( _ )";
+    let detector = DetectorBuilder::new(&MODELS_ALL_LANGUAGES_PRELOADED)
+        .languages(ahashset!(English, Urdu))
+        .build();
+
+    let segments = detector.detect_segments(text);
+    let languages: Vec<_> = segments.iter().map(|s| s.language).collect();
+    assert_eq!(languages, [Urdu, English]);
+    assert!(text[segments[0].range.clone()].ends_with("داشت"));
+    assert_eq!(
+        &text[segments[1].range.clone()],
+        "i vote for bts ( _ ) as the _ via"
+    );
+}
+
+#[test]
+fn test_language_shares() {
+    let text = "Ich habe heute keine Zeit, weil ich arbeiten muss. I have no time today.";
+    let detector = DetectorBuilder::new(&MODELS_ALL_LANGUAGES_PRELOADED)
+        .languages(ahashset!(English, German))
+        .build();
+
+    let shares = detector.language_shares(text);
+    let languages: Vec<_> = shares.iter().map(|(l, _)| *l).collect();
+    assert_eq!(languages, [German, English]);
+    assert!((shares.iter().map(|(_, s)| s).sum::<f64>() - 1.0).abs() < 1e-9);
+    assert!(shares[0].1 > 0.6);
+}
+
 /* #[rstest(
     expected_language,
     text,
@@ -0,0 +1,92 @@
+use langram::{bin_storage::ProbabilityEncoding, DetectorBuilder, ModelsStorage};
+
+const TEXTS: &[&str] = &[
+    "و في نفس الوقت أقول بأن الشيخ صالح لم يشر إلى مسؤولية الدولة التي تسمح لمواطنيها بملكية قنوات تبث ما تبث",
+    "Aan de fysieke gesteldheid van de aspirant-beoefenaar worden geen bijzondere eisen gesteld",
+    "Here, in a region abundant with natural beauty, golfers will surely be rewarded",
+    "Les affranchissements étaient très rares et s'ils accordaient la liberté à l'ancien esclave",
+    "Natürlich war sie kein Pferd, dachte sie, aber warum wurde sie dann geritten",
+    "अब इन्हें एक अलग प्लेट में निकाल कर गरमा-गरम आलू की सब्जी",
+    "Alla fine del secolo cambiarono nome, divenendo uno Capitano e l’altro Difensore",
+    "Dizer que não estou, significaria explicar porquê e não me apetece nada",
+    "То есть присяжные не сочли возможным осудить за соучастие в убийстве",
+    "Con frecuencia creo que Francia es malinterpretada",
+    "Med dagens stadshusmajoritet är det övervikt för ett enplanstorg med bostäder",
+    "Mezuniyet hediyesi olarak yerleşkenin kuzey batı bölümüne dikilmiş vişnelerin meyvesini",
+    "Et Sprang i Tiden",
+    "Løvenes konge",
+    "Kor gamal er ho?",
+    "Skal vi vere vener?",
+    "Балаларды жүзуге үй-рету бассейнінің үй-жайы",
+    "I know you әлем",
+    "contradicció",
+    "rozdělit",
+    "direktør",
+    "house",
+    "tõeliseks",
+    "façonnage",
+    "überrascht",
+    "minjaverðir",
+    "venerdì",
+    "aizklātā",
+    "павінен",
+    "довършат",
+];
+
+/// Compares quantized encodings with `f64`.
+/// Requires the full models, the mock models are compared by `test_mock_quantized_probabilities`.
+///
+/// `cargo test --release --test quantization -- --ignored --nocapture`
+#[test]
+#[ignore = "re-encodes the full models, requires a lot of memory"]
+fn test_quantization_accuracy_report() {
+    let models_storage = ModelsStorage::new().unwrap();
+    let detector = DetectorBuilder::new(&models_storage).build();
+    let expected: Vec<_> = TEXTS.iter().map(|t| detector.probabilities(t)).collect();
+
+    let bin_storage = models_storage.to_bin_storage().unwrap();
+    let f64_size = bin_storage.to_bytes().unwrap().len();
+
+    for (encoding, min_agreement) in [
+        (ProbabilityEncoding::U16, 0.95),
+        (ProbabilityEncoding::U8, 0.85),
+    ] {
+        let bytes = bin_storage.to_bytes_encoded(encoding).unwrap();
+        let size = bytes.len();
+        let quantized_storage = ModelsStorage::from_vec(bytes).unwrap();
+        let quantized_detector = DetectorBuilder::new(&quantized_storage).build();
+
+        let mut same_top = 0;
+        let mut error_sum = 0.0;
+        let mut error_cnt = 0;
+        for (text, expected) in TEXTS.iter().zip(expected.iter()) {
+            let probabilities = quantized_detector.probabilities(text);
+            if probabilities.first().map(|(l, _)| l) == expected.first().map(|(l, _)| l) {
+                same_top += 1;
+            }
+
+            for (language, expected_probability) in expected.iter().take(5) {
+                let Some((_, probability)) = probabilities.iter().find(|(l, _)| l == language)
+                else {
+                    continue;
+                };
+                if expected_probability.is_finite() && probability.is_finite() {
+                    error_sum += (expected_probability - probability).abs();
+                    error_cnt += 1;
+                }
+            }
+        }
+
+        let agreement = same_top as f64 / TEXTS.len() as f64;
+        println!(
+            "{encoding:?}: size {:.1}% of F64, top language agreement {:.1}%, mean log-probability error {:.5}",
+            size as f64 * 100.0 / f64_size as f64,
+            agreement * 100.0,
+            error_sum / error_cnt.max(1) as f64,
+        );
+        assert!(
+            agreement >= min_agreement,
+            "{encoding:?} agreement {agreement} < {min_agreement}"
+        );
+    }
+}
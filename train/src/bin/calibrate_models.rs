@@ -0,0 +1,90 @@
+use ::std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+use alphabet_detector::ScriptLanguage;
+use clap::Parser;
+use langram::{
+    calibration::{Calibration, CalibrationSample},
+    DetectionResult, DetectorBuilder, ModelsStorage,
+};
+
+/// Fits calibration of relative probabilities from a labelled evaluation corpus,
+/// and writes it into the models file
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Input models file
+    #[arg(short = 'i', required = true)]
+    inp: String,
+
+    /// Output models file, compressed if ends with `.br`
+    #[arg(short = 'o', required = true)]
+    out: String,
+
+    /// Evaluation corpus, lines of `text\tlanguage` (OpenLID format, `eng_Latn` or `engLatn`)
+    #[arg(short = 'c', required = true)]
+    corpus: String,
+
+    /// Text length buckets, min characters, comma separated
+    #[arg(
+        short = 'b',
+        value_delimiter = ',',
+        default_value = "0,8,16,32,64,128,256"
+    )]
+    buckets: Vec<u32>,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let models_storage = ModelsStorage::open(&args.inp).expect("models open failed");
+    let detector = DetectorBuilder::new(&models_storage).build();
+
+    let corpus = BufReader::new(File::open(&args.corpus).expect("corpus open failed"));
+    let mut samples = Vec::new();
+    for line in corpus.lines() {
+        let line = line.expect("corpus read failed");
+        let mut columns = line.split('\t');
+        let (Some(text), Some(language)) = (columns.next(), columns.next()) else {
+            continue;
+        };
+        let Some(language) = ScriptLanguage::from_str(&language.replace('_', "")) else {
+            continue;
+        };
+
+        let DetectionResult {
+            probabilities,
+            characters_count,
+            ..
+        } = detector.detect(text);
+        samples.push(CalibrationSample {
+            characters_count,
+            probabilities,
+            language,
+        });
+    }
+    println!("Samples: {}", samples.len());
+
+    let calibration = Calibration::fit(args.buckets, samples);
+    for bucket in calibration.buckets() {
+        println!(
+            "min characters {:>5}: temperature {:.4}",
+            bucket.min_characters, bucket.temperature
+        );
+    }
+
+    let encoding = models_storage.encoding();
+    let mut bin_storage = models_storage
+        .to_bin_storage()
+        .expect("models deserialize failed");
+    drop(detector);
+    drop(models_storage);
+    bin_storage.set_calibration(calibration);
+
+    let bytes = bin_storage
+        .to_bytes_encoded(encoding)
+        .expect("models serialize failed");
+    langram_train::write_models_file(Path::new(&args.out), &bytes).expect("write failed");
+}
@@ -13,8 +13,8 @@ use alphabet_detector::{
     reader::ReadChunks, slang_arr_default, ScriptLanguage, ScriptLanguageArr, UcdScript,
 };
 use cap::Cap;
-use clap::Parser;
-use langram::NgramSize;
+use clap::{Args, Parser, Subcommand};
+use langram::{IntoEnumIterator, ModelsStorage, NgramSize};
 // #[cfg(not(target_env = "msvc"))]
 // use jemallocator::Jemalloc;
 
@@ -31,14 +31,54 @@ static ALLOCATOR: Cap<::std::alloc::System> = Cap::new(::std::alloc::System, usi
 // #[global_allocator]
 // static GLOBAL: Jemalloc = Jemalloc;
 
+/// Trains models from files of texts, named by language (`engLatn`)
 #[derive(Parser)]
-#[command(version, about)]
-struct Args {
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    train: Option<TrainArgs>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Writes a models file containing only the selected languages and ngram sizes
+    Subset(SubsetArgs),
+}
+
+#[derive(Args)]
+struct TrainArgs {
+    #[arg(short = 'i', required = true)]
+    inp: String,
+
+    #[arg(short = 'o', required = true)]
+    out: String,
+}
+
+#[derive(Args)]
+struct SubsetArgs {
+    /// Input models file
     #[arg(short = 'i', required = true)]
     inp: String,
 
+    /// Output models file, compressed if ends with `.br`
     #[arg(short = 'o', required = true)]
     out: String,
+
+    /// Languages to keep, comma separated (`engLatn,deuLatn`)
+    #[arg(short = 'l', value_delimiter = ',', required = true)]
+    langs: Vec<String>,
+
+    /// Ngram sizes to keep, comma separated (`uni,bi,tri,quadri,five,word`). All if not set
+    #[arg(short = 'n', value_delimiter = ',')]
+    ngrams: Vec<NgramSize>,
 }
 
 const THREADS: usize = 8;
@@ -172,8 +212,32 @@ fn process(path: DirEntry, langs_seen: Arc<Mutex<ScriptLanguageArr<bool>>>, out_
     );
 }
 
-fn main() {
-    let args = Args::parse();
+fn subset(args: SubsetArgs) {
+    let langs: Vec<ScriptLanguage> = args
+        .langs
+        .iter()
+        .map(|l| ScriptLanguage::from_str(l).unwrap_or_else(|| panic!("Not found lang: {l}")))
+        .collect();
+    let ngram_sizes = if args.ngrams.is_empty() {
+        NgramSize::iter().collect()
+    } else {
+        args.ngrams
+    };
+
+    let models_storage = ModelsStorage::open(&args.inp).expect("models open failed");
+    let mut bin_storage = models_storage
+        .to_bin_storage()
+        .expect("models deserialize failed");
+    drop(models_storage);
+
+    bin_storage.retain(langs, ngram_sizes);
+    println!("{bin_storage:?}");
+
+    let bytes = bin_storage.to_bytes().expect("models serialize failed");
+    langram_train::write_models_file(Path::new(&args.out), &bytes).expect("write failed");
+}
+
+fn train(args: TrainArgs) {
     let paths = fs::read_dir(&args.inp).unwrap();
     let mut pool = threadpool::ThreadPool::new(THREADS);
     let langs_seen = Arc::new(Mutex::new(slang_arr_default::<bool>()));
@@ -205,3 +269,11 @@ fn main() {
 
     pool.join();
 }
+
+fn main() {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Subset(args)) => subset(args),
+        None => train(cli.train.expect("train args are required")),
+    }
+}
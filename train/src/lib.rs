@@ -5,7 +5,7 @@ mod fraction;
 mod training_model;
 mod writer;
 
-pub use writer::create_model_and_write_files;
+pub use writer::{create_model_and_write_files, write_models_file};
 
 // not possible to have const fn in traits
 #[inline]
@@ -27,6 +27,18 @@ impl FileModelWriter for FileModel {
     }
 }
 
+/// Writes the models binary, compressed if `file_path` ends with `.br`
+pub fn write_models_file(file_path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let file = File::create(file_path)?;
+    if file_path.extension().is_some_and(|ext| ext == "br") {
+        let mut compressed_file = CompressorWriter::new(file, 4096, 11, 22);
+        compressed_file.write_all(bytes)
+    } else {
+        let mut file = file;
+        file.write_all(bytes)
+    }
+}
+
 /// Creates language model files and writes them to a directory.
 pub fn create_model_and_write_files(
     out_mod_path: &Path,